@@ -1,9 +1,16 @@
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString, FromRepr, VariantArray};
-use zk_games::games::rps_basic::generate_basic_choice_hash;
-use zk_games_types::GameResult;
+use zk_games::{
+    clock::{Clock, SystemClock},
+    games::rps_basic::{
+        self, generate_basic_choice_hash, Game, GameMode, PinnedVks, Player1Info,
+        CHOICE_COMMITMENT_VERSION,
+    },
+    user::Account,
+    Config,
+};
 
-use crate::{games_data::GamesData, GAME_CLIENT_PUBKEY};
+use crate::{games_data::GamesBackend, GAME_CLIENT_PUBKEY};
 
 #[derive(
     Serialize, Deserialize, Debug, PartialEq, Eq, VariantArray, EnumString, Display, FromRepr, Clone,
@@ -17,45 +24,40 @@ pub enum Choice {
     Scissors,
 }
 
-/// Player 1 starts the game, so we want to get his username and the hash of his choice
-/// To finilize the game, player1 must send proof of his choice after player2 chose his choice
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Player1Info {
-    pub username: String,
-    pub choice_hash: [u8; 32],
-}
-
-/// Player2 joins a game, his choice can be sent as simple string
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Player2Info {
-    pub username: String,
-    pub choice: Choice,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-pub struct Game {
-    pub id: u64,
-    pub player1: Player1Info,
-    pub player2: Option<Player2Info>,
-    pub timeout: Option<u64>,
-    pub result: Option<GameResult>,
+impl From<Choice> for rps_basic::Choice {
+    fn from(choice: Choice) -> Self {
+        match choice {
+            Choice::Rock => rps_basic::Choice::Rock,
+            Choice::Paper => rps_basic::Choice::Paper,
+            Choice::Scissors => rps_basic::Choice::Scissors,
+        }
+    }
 }
 
-impl Game {
-    pub fn new(games_data: &GamesData, username: String, choice: Choice) -> Self {
-        let id = games_data.get_next_id();
-        let choice_hash =
-            generate_basic_choice_hash(&username, GAME_CLIENT_PUBKEY, id, choice as u8).unwrap();
+/// Open a new game with the next free id, committing to player1's choice
+pub fn new_game(
+    config: &Config,
+    games: &mut dyn GamesBackend,
+    account: &Account,
+    choice: Choice,
+    mode: GameMode,
+    reveal_timeout: u64,
+    open_timeout: u64,
+) -> zk_games::Result<Game> {
+    let id = games.get_next_id()?;
+    let choice_hash =
+        generate_basic_choice_hash(config, account, GAME_CLIENT_PUBKEY, id, choice as u8)?;
 
-        Game {
-            id,
-            player1: Player1Info {
-                username,
-                choice_hash,
-            },
-            player2: None,
-            timeout: None,
-            result: None,
-        }
-    }
+    Ok(Game::new(
+        id,
+        Player1Info {
+            username: account.username.clone(),
+            choice_hash,
+            commitment_version: CHOICE_COMMITMENT_VERSION,
+        },
+        reveal_timeout,
+    )
+    .with_mode(mode)
+    .with_open_until(SystemClock.now() + open_timeout)
+    .with_vks(PinnedVks::current()))
 }
@@ -1,116 +1,391 @@
-use std::{
-    fs::File,
-    io::{BufWriter, Write},
+use zk_games::{
+    clock::{Clock, SystemClock},
+    games::rps_basic::{
+        self, aggregate_basic_game_proofs, generate_basic_choice_hash,
+        generate_basic_game_compressed_proof, generate_basic_game_proof, generate_result_proof,
+        store::{open_game_store, GameFilter, GameStore},
+        verify_aggregate_proof, verify_basic_game_proof, verify_result_proof, ChoiceCommitment,
+        CompletionProgram, Game, GameMode, GameStatus, Player2Choice, Player2Info, TransitionError,
+        CHOICE_COMMITMENT_VERSION,
+    },
+    programs,
+    proof_store::{self, ProofKey},
+    user::Account,
+    Config, Error, ProofVerifier, ProverBackend,
 };
+use zk_games_types::GameResult;
 
-use serde::{Deserialize, Serialize};
-use zk_games::games::rps_basic::{calculate_result, generate_basic_game_proof};
-use zk_games_types::{GameResult, RpsBasicPublic};
+use crate::{game::Choice, GAME_CLIENT_PUBKEY, GAME_PROOF_MODE};
 
-use crate::{
-    game::{Choice, Game, Player2Info},
-    GAME_CLIENT_PUBKEY,
-};
+/// Where the client plays its games: the game store or a ledger standing for the chain
+pub trait GamesBackend {
+    fn get_next_id(&mut self) -> zk_games::Result<u64>;
 
-#[derive(Serialize, Deserialize)]
-pub struct GamesData {
-    pub games: Vec<Game>,
-}
+    fn get_games(&mut self, filter: &GameFilter) -> zk_games::Result<Vec<Game>>;
+
+    /// Open a game built by [`crate::game::new_game`]
+    fn add_game(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        game: &Game,
+    ) -> zk_games::Result<()>;
+
+    fn join_game(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+        choice: Choice,
+    ) -> zk_games::Result<()>;
 
-const GAMES_DATA_FILE: &str = "data/games.json";
-const GAME_TIMEOUT: u64 = 600000;
+    /// Player1 closes his open game before anyone joins
+    fn cancel_game(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<()>;
 
-impl Default for GamesData {
-    fn default() -> Self {
-        let file_path = std::env::current_dir().unwrap().join(GAMES_DATA_FILE);
+    /// Expire every open game nobody joined in time, returns their ids
+    fn expire_games(&mut self) -> zk_games::Result<Vec<u64>>;
 
-        // Get game data file create if doesn't exists
-        let file =
-            File::open(file_path.clone()).unwrap_or_else(|_| File::create(file_path).unwrap());
+    /// Settle a game whose opponent didn't reveal before the deadline
+    fn claim_timeout(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<GameResult>;
 
-        // Parse game data json if exists, create new one if doesn't
-        serde_json::from_reader(file).unwrap_or_else(|_| GamesData { games: vec![] })
+    /// Reveal our choice of the game, returns the result once both choices are known
+    fn reveal_choice(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<Option<GameResult>>;
+
+    /// Reveal our choice in every game waiting for it,
+    /// returns each revealed game with its result once both choices are known
+    fn reveal_all_choices(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+    ) -> zk_games::Result<Vec<(u64, Option<GameResult>)>>;
+
+    /// The public account was rotated to a new password, `old` still holds the previous secret
+    fn account_rotated(
+        &mut self,
+        _config: &Config,
+        _prover: &ProverBackend,
+        _old: &Account,
+    ) -> zk_games::Result<()> {
+        Ok(())
     }
 }
 
+/// Games of the client, every change is written to the store right away
+pub struct GamesData {
+    store: Box<dyn GameStore>,
+}
+
 impl GamesData {
-    pub fn get_next_id(&self) -> u64 {
-        if self.games.is_empty() {
-            0
-        } else {
-            self.games.last().unwrap().id + 1
-        }
+    pub fn load(config: &Config) -> zk_games::Result<Self> {
+        Ok(GamesData {
+            store: open_game_store(config)?,
+        })
     }
 
-    pub fn add_game(&mut self, game: Game) {
-        self.games.push(game);
+    fn get_game(&self, id: u64) -> zk_games::Result<Game> {
+        self.store.get(id)?.ok_or(Error::GameNotFound(id))
     }
+}
 
-    pub fn get_game(&self, id: u64) -> Option<&Game> {
-        self.games.iter().find(|game| game.id == id)
+impl GamesBackend for GamesData {
+    fn get_next_id(&mut self) -> zk_games::Result<u64> {
+        self.store.next_id()
     }
 
-    pub fn get_game_mut(&mut self, id: u64) -> Option<&mut Game> {
-        self.games.iter_mut().find(|game| game.id == id)
+    fn get_games(&mut self, filter: &GameFilter) -> zk_games::Result<Vec<Game>> {
+        self.store.list(filter)
     }
 
-    pub fn get_games(&self) -> &Vec<Game> {
-        &self.games
+    fn add_game(
+        &mut self,
+        _prover: &ProverBackend,
+        _account: &Account,
+        game: &Game,
+    ) -> zk_games::Result<()> {
+        self.store.create(game)
+    }
+
+    fn join_game(
+        &mut self,
+        config: &Config,
+        _prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+        choice: Choice,
+    ) -> zk_games::Result<()> {
+        let previous = self.get_game(id)?;
+        let mut game = previous.clone();
+        game.check_join(&account.username, game.mode, SystemClock.now())?;
+
+        // In hidden mode player2 commits to his choice and reveals it later, like player1
+        let choice = match game.mode {
+            GameMode::Plain => Player2Choice::Plain(choice.into()),
+            GameMode::Hidden => Player2Choice::Hidden {
+                choice_hash: generate_basic_choice_hash(
+                    config,
+                    account,
+                    GAME_CLIENT_PUBKEY,
+                    id,
+                    choice as u8,
+                )?,
+                commitment_version: CHOICE_COMMITMENT_VERSION,
+                revealed: None,
+            },
+        };
+
+        game.join(
+            Player2Info {
+                username: account.username.clone(),
+                choice,
+            },
+            SystemClock.now(),
+        )?;
+        self.store.update(&previous, &game)
+    }
+
+    fn cancel_game(
+        &mut self,
+        _prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<()> {
+        let previous = self.get_game(id)?;
+        let mut game = previous.clone();
+
+        game.cancel(&account.username)?;
+        self.store.update(&previous, &game)
     }
 
-    pub fn join_game(&mut self, id: u64, player2_username: String, choice: Choice) {
-        if let Some(game) = self.get_game_mut(id) {
-            if game.player2.is_none() && game.player1.username != player2_username {
-                game.player2 = Some(Player2Info {
-                    username: player2_username,
-                    choice,
-                });
-                let curr_time = std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                game.timeout = Some(curr_time + GAME_TIMEOUT);
+    fn expire_games(&mut self) -> zk_games::Result<Vec<u64>> {
+        let now = SystemClock.now();
+        let open = self
+            .store
+            .list(&GameFilter::default().status(GameStatus::Open))?;
+
+        let mut expired = vec![];
+        for previous in open {
+            let mut game = previous.clone();
+            if game.expire(now).is_ok() {
+                self.store.update(&previous, &game)?;
+                expired.push(game.id);
             }
         }
+        Ok(expired)
     }
 
-    pub fn save(&self) {
-        let file_path = std::env::current_dir().unwrap().join(GAMES_DATA_FILE);
+    fn claim_timeout(
+        &mut self,
+        _prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<GameResult> {
+        let previous = self.get_game(id)?;
+        let mut game = previous.clone();
+
+        let result = game.claim_timeout(&account.username, SystemClock.now())?;
+        self.store.update(&previous, &game)?;
+        Ok(result)
+    }
+
+    fn reveal_choice(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<Option<GameResult>> {
+        let previous = self.get_game(id)?;
+        let mut game = previous.clone();
+
+        // Don't pay for a proof the game would refuse anyway
+        game.check_reveal(&account.username, SystemClock.now())?;
+        let commitment = ChoiceCommitment::of(&game, &account.username, GAME_CLIENT_PUBKEY)
+            .ok_or_else(|| TransitionError::NotAPlayer {
+                game_id: id,
+                username: account.username.clone(),
+            })?;
+
+        // Once player2's choice is known, player1 proves the result itself
+        if account.username == game.player1.username {
+            if let Some(player2_choice) = game.player2().and_then(|p| p.choice.choice()) {
+                // Our rps-result build must be accepted by the game, proofs are checked against it
+                let vk_hash = programs::RPS_RESULT.vk_hash();
+                game.check_vk(CompletionProgram::Result, vk_hash, &config.accepted_vks)?;
+                let (proof, public_values, _vk) = generate_result_proof(
+                    config,
+                    prover,
+                    account,
+                    &commitment,
+                    player2_choice as u8,
+                    GAME_PROOF_MODE,
+                )?;
+                let verified = verify_result_proof(
+                    &ProofVerifier::Strict,
+                    GAME_PROOF_MODE,
+                    vk_hash,
+                    &proof,
+                    &public_values,
+                    &commitment,
+                    player2_choice as u8,
+                )?;
+                proof_store::save_proof(
+                    config,
+                    ProofKey::Game(id),
+                    &programs::RPS_RESULT,
+                    vk_hash,
+                    GAME_PROOF_MODE,
+                    &proof,
+                    &public_values,
+                )?;
+
+                let game_result =
+                    game.settle(&account.username, verified.result, SystemClock.now())?;
+                self.store.update(&previous, &game)?;
+                return Ok(Some(game_result));
+            }
+        }
+
+        // Our rps-basic build must be accepted by the game, the proof is checked against it
+        let vk_hash = programs::RPS_BASIC.vk_hash();
+        game.check_vk(CompletionProgram::Reveal, vk_hash, &config.accepted_vks)?;
+
+        // Generate choice proof locally, only we know the secret behind the choice hash
+        let (proof, public_values, _vk) =
+            generate_basic_game_proof(config, prover, account, &commitment, GAME_PROOF_MODE)?;
+
+        // Verify the proof against that vk and our game before trusting the choice
+        let verified = verify_basic_game_proof(
+            &ProofVerifier::Strict,
+            GAME_PROOF_MODE,
+            vk_hash,
+            &proof,
+            &public_values,
+            &commitment,
+        )?;
+        proof_store::save_proof(
+            config,
+            ProofKey::Game(id),
+            &programs::RPS_BASIC,
+            vk_hash,
+            GAME_PROOF_MODE,
+            &proof,
+            &public_values,
+        )?;
+        let choice = rps_basic::Choice::try_from(verified.choice).map_err(|_| {
+            Error::InvalidPublicValues(format!("invalid choice {}", verified.choice))
+        })?;
 
-        // Save games to a file or database
-        let file = File::create(file_path).unwrap();
-        let mut writer = BufWriter::new(file);
-        serde_json::to_writer(&mut writer, &self).unwrap();
-        writer.flush().unwrap();
+        // Settle the game once both choices are revealed
+        let game_result = game.reveal(&account.username, choice, SystemClock.now())?;
+        self.store.update(&previous, &game)?;
+        Ok(game_result)
     }
 
-    pub fn calculate_result(&mut self, id: u64) -> Result<GameResult, String> {
-        if let Some(game) = self.get_game_mut(id) {
-            // Generate choice proof locally
-            let (_proof, public_values, _vk) = generate_basic_game_proof(
-                &game.player1.username,
-                GAME_CLIENT_PUBKEY,
-                id,
-                game.player1.choice_hash,
-            )
-            .unwrap();
-
-            // TODO: Verify the proof
-            // Groth16Verifier::verify(&proof, &public_values, &vk, *sp1_verifier::GROTH16_VK_BYTES)
-            //     .unwrap();
-
-            // Get the game result
-            let public_values: RpsBasicPublic = public_values.into();
-            let game_result = calculate_result(
-                public_values.choice as u8,
-                game.player2.clone().unwrap().choice as u8,
-            );
-
-            // Update the game result
-            game.result = Some(game_result.clone());
-            Ok(game_result)
-        } else {
-            return Err(format!("Game with ID {} not found", id));
+    /// A single aggregated proof reveals every game
+    fn reveal_all_choices(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+    ) -> zk_games::Result<Vec<(u64, Option<GameResult>)>> {
+        let now = SystemClock.now();
+        let pending = self
+            .store
+            .list(
+                &GameFilter::default()
+                    .status(GameStatus::Joined)
+                    .player(&account.username),
+            )?
+            .into_iter()
+            .filter(|game| game.check_reveal(&account.username, now).is_ok())
+            // The aggregated reveals are proven by our rps-basic build, the game must accept it
+            .filter(|game| {
+                game.check_vk(
+                    CompletionProgram::Reveal,
+                    programs::RPS_BASIC.vk_hash(),
+                    &config.accepted_vks,
+                )
+                .is_ok()
+            })
+            .filter_map(|game| ChoiceCommitment::of(&game, &account.username, GAME_CLIENT_PUBKEY))
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return Ok(vec![]);
         }
+
+        // One compressed reveal per game, all verified inside the aggregation program
+        let proofs = pending
+            .iter()
+            .map(|commitment| {
+                generate_basic_game_compressed_proof(config, prover, account, commitment)
+            })
+            .collect::<zk_games::Result<Vec<_>>>()?;
+        let (proof, public_values, _vk) =
+            aggregate_basic_game_proofs(prover, proofs, GAME_PROOF_MODE)?;
+
+        // Verify the aggregated proof once, then settle every game it reveals
+        let verified = verify_aggregate_proof(
+            &ProofVerifier::Strict,
+            GAME_PROOF_MODE,
+            &proof,
+            &public_values,
+            GAME_CLIENT_PUBKEY,
+        )?;
+
+        // The same aggregated proof backs every game it reveals
+        for reveal in &verified.reveals {
+            proof_store::save_proof(
+                config,
+                ProofKey::Game(reveal.game_id),
+                &programs::RPS_AGGREGATE,
+                programs::RPS_AGGREGATE.vk_hash(),
+                GAME_PROOF_MODE,
+                &proof,
+                &public_values,
+            )?;
+        }
+
+        let now = SystemClock.now();
+        verified
+            .reveals
+            .into_iter()
+            .map(|reveal| {
+                let previous = self.get_game(reveal.game_id)?;
+                let mut game = previous.clone();
+                if game.commitment_of(&account.username).map(|(hash, _)| hash)
+                    != Some(reveal.choice_hash)
+                {
+                    return Err(Error::InvalidPublicValues(format!(
+                        "reveal of game {} doesn't match its choice hash",
+                        reveal.game_id
+                    )));
+                }
+                let choice = rps_basic::Choice::try_from(reveal.choice).map_err(|_| {
+                    Error::InvalidPublicValues(format!("invalid choice {}", reveal.choice))
+                })?;
+
+                let game_result = game.reveal(&account.username, choice, now)?;
+                self.store.update(&previous, &game)?;
+                Ok((reveal.game_id, game_result))
+            })
+            .collect()
     }
 }
@@ -0,0 +1,290 @@
+use std::path::Path;
+
+use sp1_sdk::SP1ProofWithPublicValues;
+use zk_games::{
+    games::rps_basic::{
+        generate_basic_choice_hash, generate_basic_game_proof, store::GameFilter, ChoiceCommitment,
+        CompletionProgram, Game, GameMode, GameStatus, TransitionError, CHOICE_COMMITMENT_VERSION,
+        DEFAULT_OPEN_TIMEOUT,
+    },
+    programs,
+    proof_store::{self, ProofKey},
+    registry::get_public_account,
+    user::Account,
+    zk::{generate_login_proof, generate_rotate_proof},
+    Config, Error, ProverBackend,
+};
+use zk_games_ledger::{EventKind, JoinChoice, Ledger, LedgerEvent, LedgerFile, Transaction};
+use zk_games_types::GameResult;
+
+use crate::{game::Choice, games_data::GamesBackend, GAME_CLIENT_PUBKEY, GAME_PROOF_MODE};
+
+/// Games kept by the ledger, every change is a transaction backed by a proof
+pub struct LedgerGames {
+    ledger: Ledger,
+    file: LedgerFile,
+}
+
+impl LedgerGames {
+    /// Open the ledger and register the account on it the first time
+    pub fn open(
+        path: &Path,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+    ) -> zk_games::Result<Self> {
+        let file = LedgerFile::open(path)?;
+        let mut ledger =
+            Ledger::new(GAME_CLIENT_PUBKEY).with_accepted_vks(config.accepted_vks.clone());
+        file.load(&mut ledger)?;
+
+        let mut games = LedgerGames { ledger, file };
+        if games.ledger.account(&account.username).is_none() {
+            let public_account = get_public_account(config, &account.username)?;
+            let proof = games.login_proof(prover, account, public_account.login_hash)?;
+            games.submit(Transaction::Register {
+                account: public_account,
+                proof,
+            })?;
+        }
+
+        Ok(games)
+    }
+
+    fn submit(&mut self, transaction: Transaction) -> zk_games::Result<Vec<LedgerEvent>> {
+        self.file
+            .transact(&mut self.ledger, |ledger| ledger.submit(transaction))
+    }
+
+    /// Login proof answering a fresh challenge of the ledger, it only takes SNARKs
+    fn login_proof(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        login_hash: [u8; 32],
+    ) -> zk_games::Result<SP1ProofWithPublicValues> {
+        let challenge = self.file.transact(&mut self.ledger, |ledger| {
+            Ok(ledger.issue_challenge(&account.username))
+        })?;
+
+        generate_login_proof(prover, account, login_hash, &challenge, GAME_PROOF_MODE)
+    }
+
+    /// Proof the account is the sender of a transaction, it can't be used twice
+    fn auth(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+    ) -> zk_games::Result<SP1ProofWithPublicValues> {
+        let login_hash = self
+            .ledger
+            .account(&account.username)
+            .map(|public_account| public_account.login_hash)
+            .ok_or_else(|| Error::AccountNotFound(account.username.clone()))?;
+
+        self.login_proof(prover, account, login_hash)
+    }
+
+    /// Latest version of a game
+    fn get_game(&mut self, id: u64) -> zk_games::Result<Game> {
+        self.file.load(&mut self.ledger)?;
+        self.ledger.game(id).cloned().ok_or(Error::GameNotFound(id))
+    }
+}
+
+impl GamesBackend for LedgerGames {
+    fn get_next_id(&mut self) -> zk_games::Result<u64> {
+        self.file.load(&mut self.ledger)?;
+        Ok(self.ledger.next_game_id())
+    }
+
+    fn get_games(&mut self, filter: &GameFilter) -> zk_games::Result<Vec<Game>> {
+        self.file.load(&mut self.ledger)?;
+        Ok(self.ledger.games(filter).into_iter().cloned().collect())
+    }
+
+    fn add_game(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        game: &Game,
+    ) -> zk_games::Result<()> {
+        // The ledger opens the game on its own clock
+        let open_timeout = game.open_until.map_or(DEFAULT_OPEN_TIMEOUT, |open_until| {
+            open_until.saturating_sub(self.ledger.now())
+        });
+
+        let auth = self.auth(prover, account)?;
+        self.submit(Transaction::CreateGame {
+            auth,
+            game_id: game.id,
+            choice_hash: game.player1.choice_hash,
+            commitment_version: game.player1.commitment_version,
+            mode: game.mode,
+            reveal_timeout: game.reveal_timeout,
+            open_timeout,
+        })?;
+        Ok(())
+    }
+
+    fn join_game(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+        choice: Choice,
+    ) -> zk_games::Result<()> {
+        let game = self.get_game(id)?;
+        game.check_join(&account.username, game.mode, self.ledger.now())?;
+
+        let choice = match game.mode {
+            GameMode::Plain => JoinChoice::Plain(choice.into()),
+            GameMode::Hidden => JoinChoice::Hidden {
+                choice_hash: generate_basic_choice_hash(
+                    config,
+                    account,
+                    GAME_CLIENT_PUBKEY,
+                    id,
+                    choice as u8,
+                )?,
+                commitment_version: CHOICE_COMMITMENT_VERSION,
+            },
+        };
+
+        let auth = self.auth(prover, account)?;
+        self.submit(Transaction::JoinGame {
+            auth,
+            game_id: id,
+            choice,
+        })?;
+        Ok(())
+    }
+
+    fn cancel_game(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<()> {
+        let auth = self.auth(prover, account)?;
+        self.submit(Transaction::CancelGame { auth, game_id: id })?;
+        Ok(())
+    }
+
+    fn expire_games(&mut self) -> zk_games::Result<Vec<u64>> {
+        let events = self
+            .file
+            .transact(&mut self.ledger, |ledger| Ok(ledger.expire_games()))?;
+
+        Ok(events
+            .into_iter()
+            .filter_map(|event| match event.kind {
+                EventKind::GameExpired { game_id } => Some(game_id),
+                _ => None,
+            })
+            .collect())
+    }
+
+    fn claim_timeout(
+        &mut self,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<GameResult> {
+        let auth = self.auth(prover, account)?;
+        self.submit(Transaction::ClaimTimeout { auth, game_id: id })?;
+
+        self.get_game(id)?.result().ok_or(Error::GameNotFound(id))
+    }
+
+    /// Both players reveal with a rps-basic proof, the ledger settles the game
+    fn reveal_choice(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+        id: u64,
+    ) -> zk_games::Result<Option<GameResult>> {
+        let game = self.get_game(id)?;
+
+        // Don't pay for a proof the ledger would refuse anyway,
+        // it will carry the vk of our rps-basic build
+        game.check_reveal(&account.username, self.ledger.now())?;
+        game.check_vk(
+            CompletionProgram::Reveal,
+            programs::RPS_BASIC.vk_hash(),
+            &config.accepted_vks,
+        )?;
+        let commitment = ChoiceCommitment::of(&game, &account.username, GAME_CLIENT_PUBKEY)
+            .ok_or_else(|| TransitionError::NotAPlayer {
+                game_id: id,
+                username: account.username.clone(),
+            })?;
+
+        let (proof, public_values, vk_hash) =
+            generate_basic_game_proof(config, prover, account, &commitment, GAME_PROOF_MODE)?;
+        proof_store::save_proof(
+            config,
+            ProofKey::Game(id),
+            &programs::RPS_BASIC,
+            &vk_hash,
+            GAME_PROOF_MODE,
+            &proof,
+            &public_values,
+        )?;
+
+        self.submit(Transaction::Reveal {
+            game_id: id,
+            mode: GAME_PROOF_MODE,
+            vk_hash,
+            proof,
+            public_values,
+        })?;
+        Ok(self.get_game(id)?.result())
+    }
+
+    /// The ledger takes one rps-basic proof per game, each game is revealed on its own
+    fn reveal_all_choices(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        account: &Account,
+    ) -> zk_games::Result<Vec<(u64, Option<GameResult>)>> {
+        let now = self.ledger.now();
+        let pending = self
+            .get_games(
+                &GameFilter::default()
+                    .status(GameStatus::Joined)
+                    .player(&account.username),
+            )?
+            .into_iter()
+            .filter(|game| game.check_reveal(&account.username, now).is_ok())
+            .map(|game| game.id)
+            .collect::<Vec<_>>();
+
+        pending
+            .into_iter()
+            .map(|id| Ok((id, self.reveal_choice(config, prover, account, id)?)))
+            .collect()
+    }
+
+    fn account_rotated(
+        &mut self,
+        config: &Config,
+        prover: &ProverBackend,
+        old: &Account,
+    ) -> zk_games::Result<()> {
+        self.file.load(&mut self.ledger)?;
+        let old_login_hash = self
+            .ledger
+            .account(&old.username)
+            .map(|public_account| public_account.login_hash)
+            .ok_or_else(|| Error::AccountNotFound(old.username.clone()))?;
+        let account = get_public_account(config, &old.username)?;
+
+        let proof = generate_rotate_proof(prover, old, old_login_hash, &account, GAME_PROOF_MODE)?;
+        self.submit(Transaction::RotateAccount { account, proof })?;
+        Ok(())
+    }
+}
@@ -1,71 +1,215 @@
 mod game;
 mod games_data;
+mod ledger_games;
 mod menu;
 mod user;
 
-use game::{Choice, Game};
-use games_data::GamesData;
+use game::{new_game, Choice};
+use games_data::{GamesBackend, GamesData};
+use ledger_games::LedgerGames;
 use menu::show_main_menu;
 use user::handle_user_not_logged_in;
+use zk_games::{
+    clock::{Clock, SystemClock},
+    games::rps_basic::{
+        store::{import_json_games, open_game_store, GameFilter},
+        Game, GameMode,
+    },
+    prover::ProofMode,
+    registry::get_public_account,
+    user::{migrate_account, rotate_account, Account, AccountKdf},
+    Config, ProofVerifier, ProverBackend,
+};
 use zk_games_types::GameResult;
 
 pub const GAME_CLIENT_PUBKEY: &str = "5tBPvVcG2nn7jUQUW47WDbgUx96TZZ2qfzyfayZDDkbJ";
+/// Login proofs checked off-chain by the registry, a STARK is enough
+pub const LOGIN_PROOF_MODE: ProofMode = ProofMode::Compressed;
+/// Game proofs settle games and the ledger only takes these, they must stay verifiable on-chain
+pub const GAME_PROOF_MODE: ProofMode = ProofMode::Groth16;
 
-#[derive(Default)]
 struct Data {
-    user: String,
-    game_data: GamesData,
+    config: Config,
+    prover: ProverBackend,
+    account: Account,
+    game_data: Box<dyn GamesBackend>,
 }
 
 impl Data {
     pub fn init() -> Self {
         dotenv::dotenv().ok();
 
-        let mut data = Data::default();
-
-        let username = handle_user_not_logged_in();
-
-        data.set_user(username);
+        let config = Config::load().unwrap_or_else(|e| {
+            println!("Error: {}", e);
+            std::process::exit(1)
+        });
+        let prover = ProverBackend::from_env().unwrap_or_else(|e| {
+            println!("Error: {}", e);
+            std::process::exit(1)
+        });
+
+        let account = handle_user_not_logged_in(&config, &prover);
+
+        // The ledger only takes transactions of registered players, so it opens after login
+        let games: zk_games::Result<Box<dyn GamesBackend>> = match &config.ledger_file {
+            Some(path) => LedgerGames::open(path, &config, &prover, &account)
+                .map(|games| Box::new(games) as Box<dyn GamesBackend>),
+            None => GamesData::load(&config).map(|games| Box::new(games) as Box<dyn GamesBackend>),
+        };
+        let mut game_data = games.unwrap_or_else(|e| {
+            println!("Error: {}", e);
+            std::process::exit(1)
+        });
+        if let Err(e) = game_data.expire_games() {
+            println!("Error expiring games: {}", e);
+        }
+
+        let data = Data {
+            config,
+            prover,
+            account,
+            game_data,
+        };
+        if data.is_legacy_account() {
+            println!("Your account uses the legacy password hashing, upgrade it from the menu");
+        }
         data
     }
 
-    fn set_user(&mut self, username: String) {
-        self.user = username;
+    fn is_legacy_account(&self) -> bool {
+        get_public_account(&self.config, &self.account.username)
+            .is_ok_and(|account| account.kdf == AccountKdf::Legacy)
+    }
+
+    /// Move a legacy account to the salted kdf, through the same proof checked
+    /// rotation as a password change
+    fn migrate_account(&mut self, password: &str) -> zk_games::Result<()> {
+        let games = self
+            .game_data
+            .get_games(&GameFilter::default().player(&self.account.username))?;
+        let account = migrate_account(
+            &self.config,
+            &self.prover,
+            &ProofVerifier::Strict,
+            &self.account.username,
+            password,
+            &games,
+            SystemClock.now(),
+        )?;
+        let old = std::mem::replace(&mut self.account, account);
+
+        self.game_data
+            .account_rotated(&self.config, &self.prover, &old)
     }
 
     fn get_user(&self) -> String {
-        self.user.clone()
+        self.account.username.clone()
+    }
+
+    fn change_password(&mut self, new_password: &str) -> zk_games::Result<()> {
+        let games = self
+            .game_data
+            .get_games(&GameFilter::default().player(&self.account.username))?;
+        let account = rotate_account(
+            &self.config,
+            &self.prover,
+            &ProofVerifier::Strict,
+            &self.account,
+            new_password,
+            &games,
+            SystemClock.now(),
+        )?;
+        let old = std::mem::replace(&mut self.account, account);
+
+        self.game_data
+            .account_rotated(&self.config, &self.prover, &old)
+    }
+
+    fn get_games(&mut self, filter: &GameFilter) -> Vec<Game> {
+        self.game_data.get_games(filter).unwrap_or_else(|e| {
+            println!("Error loading games: {}", e);
+            vec![]
+        })
+    }
+
+    fn create_game(
+        &mut self,
+        choice: Choice,
+        mode: GameMode,
+        reveal_timeout: u64,
+        open_timeout: u64,
+    ) -> zk_games::Result<Game> {
+        let game = new_game(
+            &self.config,
+            self.game_data.as_mut(),
+            &self.account,
+            choice,
+            mode,
+            reveal_timeout,
+            open_timeout,
+        )?;
+        self.game_data
+            .add_game(&self.prover, &self.account, &game)?;
+        Ok(game)
     }
 
-    fn get_games(&self) -> &Vec<Game> {
-        self.game_data.get_games()
+    fn join_game(&mut self, id: u64, choice: Choice) -> zk_games::Result<()> {
+        self.game_data
+            .join_game(&self.config, &self.prover, &self.account, id, choice)
     }
 
-    fn create_game(&mut self, choice: Choice) -> Game {
-        let game = Game::new(&self.game_data, self.user.clone(), choice);
-        self.game_data.add_game(game.clone());
-        self.game_data.save();
-        game
+    fn cancel_game(&mut self, id: u64) -> zk_games::Result<()> {
+        self.game_data.cancel_game(&self.prover, &self.account, id)
     }
 
-    fn join_game(&mut self, id: u64, choice: Choice) {
-        self.game_data.join_game(id, self.user.clone(), choice);
-        self.game_data.save();
+    fn expire_games(&mut self) {
+        if let Err(e) = self.game_data.expire_games() {
+            println!("Error expiring games: {}", e);
+        }
     }
 
-    fn calculate_game_result(&mut self, id: u64) -> Result<GameResult, String> {
-        let result = self.game_data.calculate_result(id)?;
-        self.game_data.save();
-        Ok(result)
+    fn claim_timeout(&mut self, id: u64) -> zk_games::Result<GameResult> {
+        self.game_data
+            .claim_timeout(&self.prover, &self.account, id)
+    }
+
+    fn reveal_all_choices(&mut self) -> zk_games::Result<Vec<(u64, Option<GameResult>)>> {
+        self.game_data
+            .reveal_all_choices(&self.config, &self.prover, &self.account)
+    }
+
+    fn reveal_choice(&mut self, id: u64) -> zk_games::Result<Option<GameResult>> {
+        self.game_data
+            .reveal_choice(&self.config, &self.prover, &self.account, id)
     }
 }
 
+/// `rps-game-client import-games <games.json>` copies old JSON games into the configured store
+fn import_games(path: &str) -> zk_games::Result<usize> {
+    let config = Config::load()?;
+    let mut store = open_game_store(&config)?;
+
+    import_json_games(path, store.as_mut())
+}
+
 fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if let [_, command, path] = args.as_slice() {
+        if command == "import-games" {
+            dotenv::dotenv().ok();
+            match import_games(path) {
+                Ok(imported) => println!("Imported {} games from {}", imported, path),
+                Err(e) => println!("Error importing games: {}", e),
+            }
+            return;
+        }
+    }
+
     println!("Welcome to ZK RPS CLI!");
 
     let mut data = Data::init();
 
-    println!("You are logged in as: {}", data.user);
+    println!("You are logged in as: {}", data.get_user());
 
     show_main_menu(&mut data);
 }
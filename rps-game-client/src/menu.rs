@@ -1,10 +1,16 @@
 use std::collections::BTreeMap;
 
-use dialoguer::{theme::ColorfulTheme, Select};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
 use strum::{Display, EnumString, FromRepr, VariantArray};
+use zk_games::{
+    clock::{Clock, SystemClock},
+    games::rps_basic::{
+        store::GameFilter, GameMode, GameStatus, DEFAULT_OPEN_TIMEOUT, DEFAULT_REVEAL_TIMEOUT,
+    },
+};
 use zk_games_types::GameResult;
 
-use crate::{game::Choice, Data};
+use crate::{game::Choice, user::get_password, Data};
 
 #[derive(Debug, PartialEq, Eq, VariantArray, EnumString, Display, FromRepr)]
 enum MainMenu {
@@ -12,8 +18,18 @@ enum MainMenu {
     CreateGame,
     #[strum(to_string = "Join game")]
     JoinGame,
-    #[strum(to_string = "Complete game")]
-    CompleteGame,
+    #[strum(to_string = "Reveal choice")]
+    RevealChoice,
+    #[strum(to_string = "Reveal all pending choices")]
+    RevealAll,
+    #[strum(to_string = "Claim timeout win")]
+    ClaimTimeout,
+    #[strum(to_string = "Cancel game")]
+    CancelGame,
+    #[strum(to_string = "Change password")]
+    ChangePassword,
+    #[strum(to_string = "Upgrade account security")]
+    UpgradeAccount,
     #[strum(to_string = "Exit")]
     Exit,
 }
@@ -31,12 +47,48 @@ pub fn show_main_menu(data: &mut Data) {
     match selection {
         Some(MainMenu::CreateGame) => create_new_game(data),
         Some(MainMenu::JoinGame) => join_game(data),
-        Some(MainMenu::CompleteGame) => complete_game(data),
+        Some(MainMenu::RevealChoice) => reveal_choice(data),
+        Some(MainMenu::RevealAll) => reveal_all_choices(data),
+        Some(MainMenu::ClaimTimeout) => claim_timeout(data),
+        Some(MainMenu::CancelGame) => cancel_game(data),
+        Some(MainMenu::ChangePassword) => change_password(data),
+        Some(MainMenu::UpgradeAccount) => upgrade_account(data),
         Some(MainMenu::Exit) => std::process::exit(0),
         None => println!("Invalid selection. Please try again."),
     }
 }
 
+fn change_password(data: &mut Data) {
+    println!("Enter your new password");
+    let new_password = get_password(true);
+
+    match data.change_password(&new_password) {
+        Ok(_) => println!("Password changed successfully"),
+        Err(e) => println!("Error changing password: {}", e),
+    }
+
+    show_main_menu(data)
+}
+
+fn upgrade_account(data: &mut Data) {
+    if !data.is_legacy_account() {
+        println!("Account already uses the salted password hashing");
+        show_main_menu(data);
+        return;
+    }
+
+    println!("The upgrade proves the rotation with a SNARK, this can take a few minutes");
+    println!("Please confirm your password");
+    let password = get_password(false);
+
+    match data.migrate_account(&password) {
+        Ok(_) => println!("Account upgraded"),
+        Err(e) => println!("Error upgrading account: {}", e),
+    }
+
+    show_main_menu(data)
+}
+
 fn create_new_game(data: &mut Data) {
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Please enter your choice")
@@ -48,8 +100,36 @@ fn create_new_game(data: &mut Data) {
     match selection {
         Some(choice) => {
             let choice = Choice::from_repr(choice).unwrap();
-            let game = data.create_game(choice);
-            println!("Game was created with id: {}", game.id);
+            let mode = if Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Should the opponent hide his choice until both reveal?")
+                .default(false)
+                .interact()
+                .unwrap()
+            {
+                GameMode::Hidden
+            } else {
+                GameMode::Plain
+            };
+            let reveal_timeout_minutes: u64 = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Minutes to reveal once the opponent joined")
+                .default(DEFAULT_REVEAL_TIMEOUT / 60)
+                .interact_text()
+                .unwrap();
+            let open_timeout_hours: u64 = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Hours the game waits for an opponent")
+                .default(DEFAULT_OPEN_TIMEOUT / 3600)
+                .interact_text()
+                .unwrap();
+
+            match data.create_game(
+                choice,
+                mode,
+                reveal_timeout_minutes * 60,
+                open_timeout_hours * 3600,
+            ) {
+                Ok(game) => println!("Game was created with id: {}", game.id),
+                Err(e) => println!("Error creating game: {}", e),
+            }
         }
         None => show_main_menu(data),
     }
@@ -58,12 +138,13 @@ fn create_new_game(data: &mut Data) {
 }
 
 fn join_game(data: &mut Data) {
-    let available_games = data
-        .get_games()
+    data.expire_games();
+
+    let now = SystemClock.now();
+    let games = data.get_games(&GameFilter::default().status(GameStatus::Open));
+    let available_games = games
         .iter()
-        .filter(|g| {
-            g.result.is_none() && g.player2.is_none() && g.player1.username != data.get_user()
-        })
+        .filter(|g| g.is_joinable(now) && g.player1.username != data.get_user())
         .enumerate()
         .collect::<BTreeMap<_, _>>();
 
@@ -99,9 +180,10 @@ fn join_game(data: &mut Data) {
             match choice_selection {
                 Some(choice) => {
                     let choice = Choice::from_repr(choice).unwrap();
-                    data.join_game(game_id, choice.clone());
-
-                    println!("Game id {} joined with \"{}\" choice", game_id, choice);
+                    match data.join_game(game_id, choice.clone()) {
+                        Ok(_) => println!("Game id {} joined with \"{}\" choice", game_id, choice),
+                        Err(e) => println!("Error joining game with ID {}: {}", game_id, e),
+                    }
                     show_main_menu(data);
                 }
                 None => show_main_menu(data),
@@ -111,18 +193,31 @@ fn join_game(data: &mut Data) {
     }
 }
 
-fn complete_game(data: &mut Data) {
-    let available_games = data
-        .get_games()
+fn print_result(result: GameResult) {
+    match result {
+        GameResult::Player1 => println!("Player1 wins!"),
+        GameResult::Player2 => println!("Player2 wins!"),
+        GameResult::Draw => println!("It's a draw!"),
+        GameResult::Player1ByForfeit => println!("Player1 wins by forfeit!"),
+        GameResult::Player2ByForfeit => println!("Player2 wins by forfeit!"),
+    }
+}
+
+fn reveal_choice(data: &mut Data) {
+    let now = SystemClock.now();
+    let games = data.get_games(
+        &GameFilter::default()
+            .status(GameStatus::Joined)
+            .player(data.get_user()),
+    );
+    let available_games = games
         .iter()
-        .filter(|g| {
-            g.result.is_none() && g.player1.username == data.get_user() && g.player2.is_some()
-        })
+        .filter(|g| g.check_reveal(&data.get_user(), now).is_ok())
         .enumerate()
         .collect::<BTreeMap<_, _>>();
 
     if available_games.is_empty() {
-        println!("No games available to complete yet.");
+        println!("No games waiting for your reveal.");
         show_main_menu(data);
         return;
     }
@@ -133,16 +228,17 @@ fn complete_game(data: &mut Data) {
             (
                 num,
                 format!(
-                    "Id: {}, Player2: {}",
+                    "Id: {}, Player1: {}, Player2: {}",
                     g.id,
-                    g.player2.clone().unwrap().username
+                    g.player1.username,
+                    g.player2().unwrap().username
                 ),
             )
         })
         .collect::<BTreeMap<_, _>>();
 
     let selection = Select::with_theme(&ColorfulTheme::default())
-        .with_prompt("Select a game to join")
+        .with_prompt("Select a game to reveal your choice")
         .default(0)
         .items(&selections.values().rev().cloned().collect::<Vec<String>>()[..])
         .interact_opt()
@@ -150,64 +246,147 @@ fn complete_game(data: &mut Data) {
 
     match selection {
         Some(game_num) => {
-            println!("Completing game...");
+            println!("Revealing choice...");
 
             let game_num = selections.len() - game_num - 1;
             let game_id = available_games.get(&game_num).unwrap().id;
 
-            // Perform checks before completing the game
-            {
-                let game = data.game_data.get_game(game_id).unwrap();
-
-                // Make sure game is not finished
-                if game.result.is_some() {
-                    println!("Game with ID {} is already finished", game_id);
-                    return show_main_menu(data);
-                }
-
-                // Make sure game is not timed out
-                if game.timeout.is_some() {
-                    let curr_time = std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs();
-                    if curr_time > game.timeout.unwrap() {
-                        // TODO: Handle timeout on chain
-                        println!("Game with ID {} has timed out", game_id);
-                        return show_main_menu(data);
-                    }
+            // The game checks its own rules (status, player, deadline) before proving
+            match data.reveal_choice(game_id) {
+                Ok(Some(result)) => {
+                    println!("Game with ID {} completed successfully", game_id);
+                    print_result(result);
                 }
-
-                // Make sure that current user is player1
-                if game.player1.username != data.get_user() {
-                    println!("Current user is not player1");
-                    return show_main_menu(data);
+                Ok(None) => {
+                    println!("Choice revealed, waiting for the opponent to reveal");
                 }
-
-                // Make sure we have player2 choice
-                if game.player2.is_none() {
-                    println!("Player2 has not made a choice yet");
-                    return show_main_menu(data);
+                Err(e) => {
+                    println!("Error revealing choice of game with ID {}: {}", game_id, e);
                 }
             }
 
-            // After all checks, we calculate the result
-            match data.calculate_game_result(game_id) {
-                Ok(result) => {
-                    println!("Game with ID {} completed successfully", game_id);
-                    match result {
-                        GameResult::Player1 => println!("Player1 wins!"),
-                        GameResult::Player2 => println!("Player2 wins!"),
-                        GameResult::Draw => println!("It's a draw!"),
+            return show_main_menu(data);
+        }
+        None => show_main_menu(data),
+    }
+}
+
+fn reveal_all_choices(data: &mut Data) {
+    println!("Revealing every pending choice with a single proof...");
+
+    match data.reveal_all_choices() {
+        Ok(results) => {
+            for (game_id, result) in results {
+                match result {
+                    Some(result) => {
+                        println!("Game with ID {} completed successfully", game_id);
+                        print_result(result);
                     }
-                }
-                Err(e) => {
-                    println!("Error completing game with ID {}: {}", game_id, e);
+                    None => println!(
+                        "Choice of game with ID {} revealed, waiting for the opponent",
+                        game_id
+                    ),
                 }
             }
+        }
+        Err(e) => println!("Error revealing choices: {}", e),
+    }
 
-            return show_main_menu(data);
+    show_main_menu(data)
+}
+
+fn claim_timeout(data: &mut Data) {
+    let now = SystemClock.now();
+    let available_games = data
+        .get_games(
+            &GameFilter::default()
+                .status(GameStatus::Joined)
+                .player(data.get_user()),
+        )
+        .iter()
+        .filter(|g| g.check_claim_timeout(&data.get_user(), now).is_ok())
+        .map(|g| {
+            (
+                g.id,
+                format!(
+                    "Id: {}, Player1: {}, Player2: {}",
+                    g.id,
+                    g.player1.username,
+                    g.player2().unwrap().username
+                ),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if available_games.is_empty() {
+        println!("No games where the opponent missed the reveal deadline.");
+        show_main_menu(data);
+        return;
+    }
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a game to claim")
+        .default(0)
+        .items(
+            &available_games
+                .iter()
+                .map(|(_, item)| item.clone())
+                .collect::<Vec<String>>()[..],
+        )
+        .interact_opt()
+        .unwrap();
+
+    if let Some(game_num) = selection {
+        let game_id = available_games[game_num].0;
+        match data.claim_timeout(game_id) {
+            Ok(result) => {
+                println!("Game with ID {} settled after the reveal deadline", game_id);
+                print_result(result);
+            }
+            Err(e) => println!("Error claiming game with ID {}: {}", game_id, e),
         }
-        None => show_main_menu(data),
     }
+
+    show_main_menu(data)
+}
+
+fn cancel_game(data: &mut Data) {
+    let available_games = data
+        .get_games(
+            &GameFilter::default()
+                .status(GameStatus::Open)
+                .player(data.get_user()),
+        )
+        .iter()
+        .filter(|g| g.player1.username == data.get_user())
+        .map(|g| (g.id, format!("Id: {}", g.id)))
+        .collect::<Vec<_>>();
+
+    if available_games.is_empty() {
+        println!("No open games to cancel.");
+        show_main_menu(data);
+        return;
+    }
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select a game to cancel")
+        .default(0)
+        .items(
+            &available_games
+                .iter()
+                .map(|(_, item)| item.clone())
+                .collect::<Vec<String>>()[..],
+        )
+        .interact_opt()
+        .unwrap();
+
+    if let Some(game_num) = selection {
+        let game_id = available_games[game_num].0;
+        match data.cancel_game(game_id) {
+            Ok(_) => println!("Game with ID {} was cancelled", game_id),
+            Err(e) => println!("Error cancelling game with ID {}: {}", game_id, e),
+        }
+    }
+
+    show_main_menu(data)
 }
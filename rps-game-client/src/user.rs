@@ -1,16 +1,14 @@
-use std::{
-    fs::{self, File},
-    io::{BufWriter, Write},
-    path::Path,
-};
-
 use dialoguer::{theme::ColorfulTheme, Input, Password, Select};
-use sp1_sdk::{Prover, ProverClient, SP1Stdin};
 use strum::{Display, EnumString, FromRepr, VariantArray};
-use zk_games::user;
+use zk_games::{
+    login::LoginVerifier,
+    registry::{get_public_account, register_public_account},
+    user::{self, Account},
+    zk::generate_login_proof,
+    Config, ProverBackend,
+};
 
-use rand::{distr::Alphanumeric, Rng};
-use zk_games_types::{LoginInput, LoginPublic};
+use crate::{GAME_CLIENT_PUBKEY, LOGIN_PROOF_MODE};
 
 #[derive(Debug, PartialEq, Eq, VariantArray, EnumString, Display, FromRepr)]
 enum UserMenu {
@@ -18,13 +16,13 @@ enum UserMenu {
     Register,
     #[strum(to_string = "Log in")]
     Login,
-    #[strum(to_string = "Log in with password")]
-    LoginPass,
+    #[strum(to_string = "Recover account")]
+    Recover,
     #[strum(to_string = "Log in demo")]
     LoginDemo,
 }
 
-pub(crate) fn handle_user_not_logged_in() -> String {
+pub(crate) fn handle_user_not_logged_in(config: &Config, prover: &ProverBackend) -> Account {
     let selection = UserMenu::from_repr(
         Select::with_theme(&ColorfulTheme::default())
             .with_prompt("Not logged in:")
@@ -37,25 +35,27 @@ pub(crate) fn handle_user_not_logged_in() -> String {
     match selection {
         Some(UserMenu::LoginDemo) => {
             let username = get_user("Username:");
-            log_in_demo(username)
+            let password = get_password(false);
+            log_in_demo(config, prover, username, password)
         }
         Some(UserMenu::Login) => {
             let username = get_user("Username:");
-            log_in(username)
+            let password = get_password(false);
+            log_in(config, prover, username, password)
         }
-        Some(UserMenu::LoginPass) => {
+        Some(UserMenu::Recover) => {
             let username = get_user("Username:");
             let password = get_password(false);
-            log_in_with_pass(username, password)
+            recover(config, prover, username, password)
         }
         Some(UserMenu::Register) => {
             let username = get_user("Username:");
             let password = get_password(true);
-            register(username, password)
+            register(config, prover, username, password)
         }
         None => {
             println!("Invalid selection. Please try again.");
-            handle_user_not_logged_in()
+            handle_user_not_logged_in(config, prover)
         }
     }
 }
@@ -67,7 +67,7 @@ fn get_user(prompt: &str) -> String {
         .unwrap()
 }
 
-fn get_password(with_confirm: bool) -> String {
+pub(crate) fn get_password(with_confirm: bool) -> String {
     let theme = &ColorfulTheme::default();
     let pass_editor = Password::with_theme(theme);
     let pass_editor = pass_editor.with_prompt("Password:");
@@ -81,150 +81,122 @@ fn get_password(with_confirm: bool) -> String {
     pass_editor.interact().unwrap()
 }
 
-fn log_in_demo(username: String) -> String {
-    // Simply verify we have a secret for this user (exists in our db)
-    match zk_games::zk::get_secret(&username) {
-        Ok(_) => (),
-        Err(_) => {
-            println!("Error: {} doesn't exists", username);
-            return handle_user_not_logged_in();
+fn log_in_demo(
+    config: &Config,
+    prover: &ProverBackend,
+    username: String,
+    password: String,
+) -> Account {
+    // Simply unlock the secret of this user (exists in our db)
+    match Account::unlock(config, &username, &password) {
+        Ok(account) => account,
+        Err(e) => {
+            println!("Error: {}", e);
+            handle_user_not_logged_in(config, prover)
         }
-    };
-
-    username
+    }
 }
 
-fn log_in(username: String) -> String {
+fn log_in(config: &Config, prover: &ProverBackend, username: String, password: String) -> Account {
     println!("Logging in...");
 
-    // Read secret from local file
-    let my_secret = match zk_games::zk::get_secret(&username) {
-        Ok(secret) => secret,
+    // Unlock secret from local keystore
+    let account = match Account::unlock(config, &username, &password) {
+        Ok(account) => account,
         Err(e) => {
             println!("Error: {}", e);
-            return handle_user_not_logged_in();
+            return handle_user_not_logged_in(config, prover);
         }
     };
 
     // Read login_hash from public file (chain)
-    let login_hash = match get_login_hash(&username) {
-        Ok(hash) => hash,
+    let login_hash = match get_public_account(config, &username) {
+        Ok(account) => account.login_hash,
         Err(e) => {
             println!("Error: {}", e);
-            return handle_user_not_logged_in();
+            return handle_user_not_logged_in(config, prover);
         }
     };
 
-    // Generate random string for login session
-    let random_string: String = rand::rng()
-        .sample_iter(&Alphanumeric)
-        .take(7)
-        .map(char::from)
-        .collect();
-
-    let login_proof_input = LoginInput {
-        secret: my_secret,
-        login_hash,
-        random_string: random_string.clone(),
+    // The registry verifies logins, it issues the login session challenge
+    let verifier = match LoginVerifier::new(config.clone(), GAME_CLIENT_PUBKEY) {
+        Ok(verifier) => verifier,
+        Err(e) => {
+            println!("Error: {}", e);
+            return handle_user_not_logged_in(config, prover);
+        }
     };
-
-    // Start generating the proof
-    let client = ProverClient::builder().mock().build();
-    let login_elf = fs::read(Path::new(
-        "/mnt/extra/Projects/solana/zk-games/zk-games-programs/login/elf/login-zk-program",
-    ))
-    .unwrap();
-    let (pk, vk) = client.setup(login_elf.as_slice());
-
-    let mut stdin = SP1Stdin::new();
-    stdin.write(&login_proof_input);
-
-    // Generate the proof
-    let mut proof = client
-        .prove(&pk, &stdin)
-        .groth16()
-        .run()
-        .expect("Groth16 proof generation failed");
-
-    // Verify the proof locally
-    client
-        .verify(&proof, &vk)
-        .expect("Groth16 proof verification failed");
-
-    // Get proof public values
-    let res = proof.public_values.read::<LoginPublic>();
-
-    // Make sure proof public info is correct
-    if res.login_hash != login_hash && res.random_string != random_string {
-        println!("Error: Login failed");
-        return handle_user_not_logged_in();
-    }
-
-    // The proof is verified and the login is successful
-    username
-}
-
-fn log_in_with_pass(username: String, password: String) -> String {
-    println!("Logging in with password...");
-
-    match user::create_account(username.as_str(), password.as_str()) {
-        Ok(_) => (),
+    let challenge = match verifier.issue_challenge(&username) {
+        Ok(challenge) => challenge,
         Err(e) => {
             println!("Error: {}", e);
-            return handle_user_not_logged_in();
+            return handle_user_not_logged_in(config, prover);
         }
     };
 
-    log_in(username)
-}
-
-fn register(username: String, password: String) -> String {
-    let login_hash = match user::create_account(&username, &password) {
-        Ok(hash) => hash,
+    // Generate the proof
+    let proof =
+        match generate_login_proof(prover, &account, login_hash, &challenge, LOGIN_PROOF_MODE) {
+            Ok(res) => res,
+            Err(e) => {
+                println!("Error: {}", e);
+                return handle_user_not_logged_in(config, prover);
+            }
+        };
+
+    // The verifier checks the proof and tells us who logged in
+    match verifier.verify(&proof) {
+        Ok(verified_username) if verified_username == username => (),
+        Ok(_) => {
+            println!("Error: Login failed");
+            return handle_user_not_logged_in(config, prover);
+        }
         Err(e) => {
             println!("Error: {}", e);
-            return handle_user_not_logged_in();
+            return handle_user_not_logged_in(config, prover);
         }
-    };
+    }
 
-    save_public_login_hash(&username, login_hash);
-    username
+    // The proof is verified and the login is successful
+    account
 }
 
-// TODO: Save login_hash and username on Solana
-fn save_public_login_hash(username: &str, login_hash: [u8; 32]) {
-    let user_public_path = Path::new(user::PUBLIC_PLAYERS_PATH).join(username);
+fn recover(config: &Config, prover: &ProverBackend, username: String, password: String) -> Account {
+    println!("Recovering account...");
 
-    if !user_public_path.exists() {
-        std::fs::create_dir_all(&user_public_path).unwrap();
+    // Rebuild the local secret from the password, checked against the public login hash
+    if let Err(e) = user::recover_account(config, &username, &password) {
+        println!("Error: {}", e);
+        return handle_user_not_logged_in(config, prover);
     }
 
-    let filename = user_public_path.join(user::LOGIN_HASH_FILENAME);
-
-    // Save games to a file or database
-    let file = File::create(filename).unwrap();
-    let mut writer = BufWriter::new(file);
-    writer.write(&login_hash).unwrap();
-    writer.flush().unwrap();
+    log_in(config, prover, username, password)
 }
 
-// TODO: get login_hash from Solana
-fn get_login_hash(username: &str) -> Result<[u8; 32], String> {
-    let user_public_path = Path::new(user::PUBLIC_PLAYERS_PATH).join(username);
-
-    if !user_public_path.exists() {
-        std::fs::create_dir_all(&user_public_path).unwrap();
+fn register(
+    config: &Config,
+    prover: &ProverBackend,
+    username: String,
+    password: String,
+) -> Account {
+    // Make sure the username is not taken before creating anything locally
+    if get_public_account(config, &username).is_ok() {
+        println!("Error: {}", zk_games::Error::AccountExists(username));
+        return handle_user_not_logged_in(config, prover);
     }
 
-    let filename = user_public_path.join(user::LOGIN_HASH_FILENAME);
-
-    if !filename.exists() {
-        return Err("Error: User doesn't exists".to_string());
+    let res = user::create_account(config, &username, &password)
+        .and_then(|account| register_public_account(config, &account));
+    if let Err(e) = res {
+        println!("Error: {}", e);
+        return handle_user_not_logged_in(config, prover);
     }
 
-    Ok(*std::fs::read(filename)
-        .unwrap()
-        .as_slice()
-        .first_chunk::<32>()
-        .unwrap())
+    match Account::unlock(config, &username, &password) {
+        Ok(account) => account,
+        Err(e) => {
+            println!("Error: {}", e);
+            handle_user_not_logged_in(config, prover)
+        }
+    }
 }
@@ -1,87 +1,874 @@
 #[cfg(test)]
 mod test {
-    use std::{
-        fs::File,
-        io::{BufWriter, Write},
-        path::Path,
-    };
-
-    use sp1_sdk::{include_elf, Prover, ProverClient, SP1Stdin};
-
-    use zk_games::user;
-    use zk_games_types::{LoginInput, LoginPublic};
+    use std::sync::Arc;
 
-    const LOGIN_ELF: &[u8] = include_elf!("login-zk-program");
-    // const RPS_BASIC_ELF: &[u8] = include_elf!("rps-basic-zk-program");
+    use sp1_sdk::SP1Stdin;
 
-    fn save_public_login_hash(username: &str, login_hash: [u8; 32]) {
-        let user_local_path = Path::new(user::PUBLIC_PLAYERS_PATH).join(username);
-
-        if !user_local_path.exists() {
-            std::fs::create_dir_all(&user_local_path).unwrap();
-        }
-
-        let filename = user_local_path.join(user::LOGIN_HASH_FILENAME);
-
-        // Save games to a file or database
-        let file = File::create(filename).unwrap();
-        let mut writer = BufWriter::new(file);
-        writer.write(&login_hash).unwrap();
-        writer.flush().unwrap();
-    }
+    use zk_games::{
+        clock::ManualClock,
+        games::rps_basic,
+        login::{LoginChallenge, LoginVerifier},
+        programs,
+        proof_store::{self, ProofKey},
+        prover::ProofMode,
+        registry,
+        testing::{TestDir, TEST_PASSWORD},
+        user, zk, Config, Error, ProofVerifier, ProverBackend,
+    };
+    use zk_games_ledger::{EventKind, JoinChoice, Ledger, Transaction};
+    use zk_games_types::{GameResult, LoginInput, LoginPublic};
 
     #[test]
     fn example() {
         let username = "username";
-        let password = "some_password1234";
 
-        // Generate hashes for username
-        let login_hash = user::create_account(username, password).unwrap();
-        // Save the public login_hash to our local data
-        save_public_login_hash(username, login_hash);
+        // Keep test data away from the real data dir
+        let dir = TestDir::new("game");
+        let config = dir.config();
 
-        // try to generate proof
-        let client = ProverClient::builder().mock().build();
-        let (pk, vk) = client.setup(LOGIN_ELF);
-
-        println!(
-            "Login Program Verification Key Bytes {:?}",
-            sp1_sdk::HashableKey::bytes32(&vk)
-        );
-
-        let secret_path = Path::new(user::LOCAL_PLAYERS_PATH)
-            .join(username)
-            .join(user::SECRET_FILENAME);
-        let my_secret = std::fs::read(secret_path).unwrap();
+        // Create the account, register its public record and unlock the local keystore
+        let (account, my_account) = dir.player(username);
+        let login_hash = account.login_hash;
 
         let login_input = LoginInput {
-            secret: *my_secret
-                .as_slice()
-                .first_chunk::<32>()
-                .expect("secret should be length of 32"),
+            username: username.to_string(),
             login_hash,
             random_string: "1234".to_string(),
+            client_pubkey: "test-client".to_string(),
+            expires_at: u64::MAX,
+            secret: *my_account.secret(),
         };
 
         let mut stdin = SP1Stdin::new();
         stdin.write(&login_input);
 
-        let mut proof = client
-            .prove(&pk, &stdin)
-            .groth16()
-            .run()
-            .expect("Groth16 proof generation failed");
+        // try to generate proof
+        let prover = ProverBackend::Mock;
+        let mut proof = prover
+            .prove(&programs::LOGIN, &stdin, ProofMode::Groth16)
+            .unwrap();
 
+        println!(
+            "Login Program Verification Key Bytes {:?}",
+            programs::LOGIN.vk_hash()
+        );
+
+        std::fs::create_dir_all(&config.proofs_dir).unwrap();
         proof
-            .save("/mnt/extra/Projects/solana/zk-games/data/proofs/username-1234.bin")
+            .save(config.proofs_dir.join("username-1234.bin"))
             .unwrap();
 
-        client
-            .verify(&proof, &vk)
+        ProofVerifier::AcceptMock
+            .verify(&proof, &programs::LOGIN)
             .expect("Groth16 proof verification failed");
 
         let res = proof.public_values.read::<LoginPublic>();
 
         println!("Login Result: {:?}", res);
     }
+
+    #[test]
+    fn login_verifier() {
+        let username = "verified";
+
+        let dir = TestDir::new("login-verifier");
+        let config = dir.config();
+        let (account, my_account) = dir.player(username);
+
+        let prover = ProverBackend::Mock;
+        let clock = Arc::new(ManualClock::new(1_000));
+        let new_verifier = || {
+            LoginVerifier::new(config.clone(), "test-client")
+                .unwrap()
+                .with_verifier(ProofVerifier::AcceptMock)
+                .with_clock(clock.clone())
+                .with_ttl(60)
+        };
+        let verifier = new_verifier();
+
+        // Valid login, the challenges are kept by the registry, not by this verifier
+        let challenge = new_verifier().issue_challenge(username).unwrap();
+        let proof = zk::generate_login_proof(
+            &prover,
+            &my_account,
+            account.login_hash,
+            &challenge,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        assert_eq!(verifier.verify(&proof).unwrap(), username);
+
+        // The same proof can't be used twice
+        assert!(matches!(
+            verifier.verify(&proof),
+            Err(Error::ChallengeReplayed)
+        ));
+
+        // A challenge the verifier didn't issue is rejected
+        let mut made_up = challenge.clone();
+        made_up.random_string = "made-up".to_string();
+        let proof = zk::generate_login_proof(
+            &prover,
+            &my_account,
+            account.login_hash,
+            &made_up,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        assert!(matches!(
+            verifier.verify(&proof),
+            Err(Error::UnknownChallenge)
+        ));
+
+        // A proof meant for another client is rejected
+        let mut other_client = verifier.issue_challenge(username).unwrap();
+        other_client.client_pubkey = "other-client".to_string();
+        let proof = zk::generate_login_proof(
+            &prover,
+            &my_account,
+            account.login_hash,
+            &other_client,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        assert!(matches!(
+            verifier.verify(&proof),
+            Err(Error::WrongAudience(_))
+        ));
+
+        // Expired challenge
+        let challenge = verifier.issue_challenge(username).unwrap();
+        let proof = zk::generate_login_proof(
+            &prover,
+            &my_account,
+            account.login_hash,
+            &challenge,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        clock.advance(61);
+        assert!(matches!(
+            verifier.verify(&proof),
+            Err(Error::ChallengeExpired)
+        ));
+    }
+
+    #[test]
+    fn rps_result() {
+        let username = "player1";
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("rps-result");
+        let config = dir.config();
+        let (_, account) = dir.player(username);
+
+        // Player1 commits to paper, player2 plays rock in plain text
+        let choice_hash = rps_basic::generate_basic_choice_hash(
+            &config,
+            &account,
+            client_pubkey,
+            0,
+            rps_basic::Choice::Paper as u8,
+        )
+        .unwrap();
+        let mut game = rps_basic::Game::new(
+            0,
+            rps_basic::Player1Info {
+                username: username.to_string(),
+                choice_hash,
+                commitment_version: rps_basic::CHOICE_COMMITMENT_VERSION,
+            },
+            rps_basic::DEFAULT_REVEAL_TIMEOUT,
+        );
+        game.join(
+            rps_basic::Player2Info {
+                username: "player2".to_string(),
+                choice: rps_basic::Player2Choice::Plain(rps_basic::Choice::Rock),
+            },
+            0,
+        )
+        .unwrap();
+        let commitment = rps_basic::ChoiceCommitment::of(&game, username, client_pubkey).unwrap();
+
+        // The winner comes out of the proof
+        let prover = ProverBackend::Mock;
+        let (proof, public_values, vk_hash) = rps_basic::generate_result_proof(
+            &config,
+            &prover,
+            &account,
+            &commitment,
+            rps_basic::Choice::Rock as u8,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        let verified = rps_basic::verify_result_proof(
+            &ProofVerifier::AcceptMock,
+            ProofMode::Groth16,
+            &vk_hash,
+            &proof,
+            &public_values,
+            &commitment,
+            rps_basic::Choice::Rock as u8,
+        )
+        .unwrap();
+        assert_eq!(verified.result, GameResult::Player1);
+
+        // A proof against another player2 choice is rejected
+        assert!(rps_basic::verify_result_proof(
+            &ProofVerifier::AcceptMock,
+            ProofMode::Groth16,
+            &vk_hash,
+            &proof,
+            &public_values,
+            &commitment,
+            rps_basic::Choice::Scissors as u8,
+        )
+        .is_err());
+
+        game.settle(username, verified.result, 1).unwrap();
+        assert_eq!(game.result(), Some(GameResult::Player1));
+    }
+
+    #[test]
+    fn rps_basic_cycles() {
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("rps-basic-cycles");
+        let config = dir.config();
+        let (_, account) = dir.player("player1");
+
+        // Scissors used to be the most expensive choice, it was hashed last
+        let choice_hash = rps_basic::generate_basic_choice_hash(
+            &config,
+            &account,
+            client_pubkey,
+            0,
+            rps_basic::Choice::Scissors as u8,
+        )
+        .unwrap();
+        let commitment = new_commitment(client_pubkey, 0, choice_hash);
+
+        let prover = ProverBackend::Mock;
+        let cycles = rps_basic::basic_game_cycles(&config, &prover, &account, &commitment).unwrap();
+        println!("rps-basic reveal cycles: {cycles}");
+
+        let (proof, public_values, vk_hash) = rps_basic::generate_basic_game_proof(
+            &config,
+            &prover,
+            &account,
+            &commitment,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        let public_values = rps_basic::verify_basic_game_proof(
+            &ProofVerifier::AcceptMock,
+            ProofMode::Groth16,
+            &vk_hash,
+            &proof,
+            &public_values,
+            &commitment,
+        )
+        .unwrap();
+        assert_eq!(public_values.choice, rps_basic::Choice::Scissors as u8);
+    }
+
+    #[test]
+    fn strict_verifier() {
+        let username = "player1";
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("strict-verifier");
+        let config = dir.config();
+        let (public_account, account) = dir.player(username);
+
+        let choice_hash = rps_basic::generate_basic_choice_hash(
+            &config,
+            &account,
+            client_pubkey,
+            0,
+            rps_basic::Choice::Rock as u8,
+        )
+        .unwrap();
+        let commitment = new_commitment(client_pubkey, 0, choice_hash);
+        let prover = ProverBackend::Mock;
+        let (proof, public_values, vk_hash) = rps_basic::generate_basic_game_proof(
+            &config,
+            &prover,
+            &account,
+            &commitment,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        let verify = |verifier: &ProofVerifier, mode: ProofMode, proof: &[u8]| {
+            rps_basic::verify_basic_game_proof(
+                verifier,
+                mode,
+                &vk_hash,
+                proof,
+                &public_values,
+                &commitment,
+            )
+        };
+
+        // Mock proofs prove nothing, only the test verifier takes them
+        assert!(verify(&ProofVerifier::AcceptMock, ProofMode::Groth16, &proof).is_ok());
+        for mode in [ProofMode::Groth16, ProofMode::Plonk] {
+            assert!(matches!(
+                verify(&ProofVerifier::Strict, mode, &proof),
+                Err(Error::Verification(_))
+            ));
+        }
+
+        // Made up bytes are rejected by every verifier, whatever the claimed mode
+        let garbage = vec![0xab; 356];
+        for verifier in [ProofVerifier::Strict, ProofVerifier::AcceptMock] {
+            for mode in [
+                ProofMode::Core,
+                ProofMode::Compressed,
+                ProofMode::Plonk,
+                ProofMode::Groth16,
+            ] {
+                assert!(matches!(
+                    verify(&verifier, mode, &garbage),
+                    Err(Error::Verification(_))
+                ));
+            }
+        }
+
+        // Same for a mock login proof
+        let challenge = LoginChallenge::new(username, client_pubkey, u64::MAX);
+        let proof = zk::generate_login_proof(
+            &prover,
+            &account,
+            public_account.login_hash,
+            &challenge,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        assert!(ProofVerifier::Strict
+            .verify(&proof, &programs::LOGIN)
+            .is_err());
+    }
+
+    /// Commitment made by `generate_basic_choice_hash`
+    fn new_commitment(
+        client_pubkey: &str,
+        game_id: u64,
+        choice_hash: [u8; 32],
+    ) -> rps_basic::ChoiceCommitment {
+        rps_basic::ChoiceCommitment {
+            client_pubkey: client_pubkey.to_string(),
+            game_id,
+            choice_hash,
+            version: rps_basic::CHOICE_COMMITMENT_VERSION,
+        }
+    }
+
+    /// Open game of `account` committed to rock
+    fn committed_game(
+        config: &Config,
+        account: &user::Account,
+        client_pubkey: &str,
+        game_id: u64,
+    ) -> rps_basic::Game {
+        let choice_hash = rps_basic::generate_basic_choice_hash(
+            config,
+            account,
+            client_pubkey,
+            game_id,
+            rps_basic::Choice::Rock as u8,
+        )
+        .unwrap();
+
+        rps_basic::Game::new(
+            game_id,
+            rps_basic::Player1Info {
+                username: account.username.clone(),
+                choice_hash,
+                commitment_version: rps_basic::CHOICE_COMMITMENT_VERSION,
+            },
+            rps_basic::DEFAULT_REVEAL_TIMEOUT,
+        )
+    }
+
+    /// Join with paper and reveal the choice of player 1 with a proof, as the client does
+    fn join_and_reveal(
+        config: &Config,
+        prover: &ProverBackend,
+        account: &user::Account,
+        client_pubkey: &str,
+        game: &mut rps_basic::Game,
+    ) {
+        game.join(
+            rps_basic::Player2Info {
+                username: "opponent".to_string(),
+                choice: rps_basic::Player2Choice::Plain(rps_basic::Choice::Paper),
+            },
+            0,
+        )
+        .unwrap();
+
+        let commitment =
+            rps_basic::ChoiceCommitment::of(game, &account.username, client_pubkey).unwrap();
+        let (proof, public_values, vk_hash) = rps_basic::generate_basic_game_proof(
+            config,
+            prover,
+            account,
+            &commitment,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        let revealed = rps_basic::verify_basic_game_proof(
+            &ProofVerifier::AcceptMock,
+            ProofMode::Groth16,
+            &vk_hash,
+            &proof,
+            &public_values,
+            &commitment,
+        )
+        .unwrap();
+
+        let choice = rps_basic::Choice::try_from(revealed.choice).unwrap();
+        game.reveal(&account.username, choice, 1).unwrap();
+    }
+
+    #[test]
+    fn rotate_account() {
+        let username = "rotating";
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("rotate-account");
+        let config = dir.config();
+        let (public_account, account) = dir.player(username);
+
+        let prover = ProverBackend::Mock;
+        let mut game = committed_game(&config, &account, client_pubkey, 0);
+
+        // The commitment is bound to the current secret, it has to be revealed first
+        let rotate = |games: &[rps_basic::Game]| {
+            user::rotate_account(
+                &config,
+                &prover,
+                &ProofVerifier::AcceptMock,
+                &account,
+                "new_password1234",
+                games,
+                1,
+            )
+        };
+        assert!(matches!(
+            rotate(&[game.clone()]),
+            Err(Error::PendingCommitments(_, ids)) if ids == vec![0]
+        ));
+        assert_eq!(
+            registry::get_public_account(&config, username).unwrap(),
+            public_account
+        );
+
+        join_and_reveal(&config, &prover, &account, client_pubkey, &mut game);
+        assert_eq!(game.result(), Some(GameResult::Player2));
+
+        let rotated = rotate(&[game]).unwrap();
+        assert_eq!(
+            registry::get_public_account(&config, username)
+                .unwrap()
+                .login_hash,
+            user::generate_login_hash(*rotated.secret())
+        );
+        assert!(matches!(
+            user::Account::unlock(&config, username, TEST_PASSWORD),
+            Err(Error::InvalidPassword(_))
+        ));
+        assert!(user::Account::unlock(&config, username, "new_password1234").is_ok());
+
+        // The proof binds the new kdf, it can't be replayed with another salt
+        let current = registry::get_public_account(&config, username).unwrap();
+        let next = registry::PublicAccount {
+            username: username.to_string(),
+            kdf: user::AccountKdf::new_argon2id(),
+            login_hash: [2; 32],
+        };
+        let proof = zk::generate_rotate_proof(
+            &prover,
+            &rotated,
+            current.login_hash,
+            &next,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+        let forged = registry::PublicAccount {
+            kdf: user::AccountKdf::new_argon2id(),
+            ..next.clone()
+        };
+        assert!(matches!(
+            registry::rotate_public_account(&config, &ProofVerifier::AcceptMock, &forged, &proof),
+            Err(Error::InvalidPublicValues(_))
+        ));
+        assert_eq!(
+            registry::get_public_account(&config, username).unwrap(),
+            current
+        );
+        registry::rotate_public_account(&config, &ProofVerifier::AcceptMock, &next, &proof)
+            .unwrap();
+    }
+
+    #[test]
+    fn migrate_account() {
+        let username = "legacy";
+        let password = TEST_PASSWORD;
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("migrate-account");
+        let config = dir.config();
+
+        // An account of the old client, with its plaintext secret
+        let legacy_secret = user::AccountKdf::Legacy
+            .derive_secret(username, password)
+            .unwrap();
+        registry::register_public_account(
+            &config,
+            &registry::PublicAccount {
+                username: username.to_string(),
+                kdf: user::AccountKdf::Legacy,
+                login_hash: user::generate_login_hash(legacy_secret),
+            },
+        )
+        .unwrap();
+        let local_dir = config.local_players_dir.join(username);
+        std::fs::create_dir_all(&local_dir).unwrap();
+        std::fs::write(local_dir.join(user::SECRET_FILENAME), legacy_secret).unwrap();
+        let legacy = user::Account::unlock(&config, username, password).unwrap();
+
+        let prover = ProverBackend::Mock;
+        let mut game = committed_game(&config, &legacy, client_pubkey, 0);
+
+        // The commitment is bound to the legacy secret, it has to be revealed first
+        let migrate = |games: &[rps_basic::Game]| {
+            user::migrate_account(
+                &config,
+                &prover,
+                &ProofVerifier::AcceptMock,
+                username,
+                password,
+                games,
+                1,
+            )
+        };
+        assert!(matches!(
+            migrate(&[game.clone()]),
+            Err(Error::PendingCommitments(_, ids)) if ids == vec![0]
+        ));
+        assert_eq!(
+            registry::get_public_account(&config, username).unwrap().kdf,
+            user::AccountKdf::Legacy
+        );
+
+        join_and_reveal(&config, &prover, &legacy, client_pubkey, &mut game);
+        assert_eq!(game.result(), Some(GameResult::Player2));
+
+        // The new secret is registered with a rotation proof
+        let migrated = migrate(&[game]).unwrap();
+        let public_account = registry::get_public_account(&config, username).unwrap();
+        assert_ne!(public_account.kdf, user::AccountKdf::Legacy);
+        assert_eq!(
+            public_account.login_hash,
+            user::generate_login_hash(*migrated.secret())
+        );
+        assert_eq!(
+            user::Account::unlock(&config, username, password)
+                .unwrap()
+                .secret(),
+            migrated.secret()
+        );
+    }
+
+    #[test]
+    fn stored_proofs() {
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("stored-proofs");
+        let config = dir.config();
+        let (_, account) = dir.player("player1");
+
+        let choice_hash = rps_basic::generate_basic_choice_hash(
+            &config,
+            &account,
+            client_pubkey,
+            3,
+            rps_basic::Choice::Rock as u8,
+        )
+        .unwrap();
+        let commitment = new_commitment(client_pubkey, 3, choice_hash);
+
+        let prover = ProverBackend::Mock;
+        let (proof, public_values, vk_hash) = rps_basic::generate_basic_game_proof(
+            &config,
+            &prover,
+            &account,
+            &commitment,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+
+        // Storing the same proof twice keeps a single record
+        let record = proof_store::save_proof(
+            &config,
+            ProofKey::Game(3),
+            &programs::RPS_BASIC,
+            &vk_hash,
+            ProofMode::Groth16,
+            &proof,
+            &public_values,
+        )
+        .unwrap();
+        proof_store::save_proof(
+            &config,
+            ProofKey::Game(3),
+            &programs::RPS_BASIC,
+            &vk_hash,
+            ProofMode::Groth16,
+            &proof,
+            &public_values,
+        )
+        .unwrap();
+
+        // What an interrupted save leaves behind is ignored
+        let game_dir = config.proofs_dir.join(proof_store::GAMES_DIRNAME).join("3");
+        std::fs::write(game_dir.join("partial.json.1-2.tmp"), b"{\"key\":").unwrap();
+
+        let records = proof_store::load_proofs(&config, &ProofKey::Game(3)).unwrap();
+        assert_eq!(records, vec![record]);
+        assert!(proof_store::load_proofs(&config, &ProofKey::Game(4))
+            .unwrap()
+            .is_empty());
+
+        // The stored proof verifies again without re-proving
+        assert_eq!(records[0].mode, ProofMode::Groth16);
+        let program = records[0].verify(&ProofVerifier::AcceptMock, &[]).unwrap();
+        assert_eq!(program.name, programs::RPS_BASIC.name);
+
+        // Any change to the record is detected
+        let mut tampered = records[0].clone();
+        tampered.public_values[0] ^= 1;
+        assert!(matches!(
+            tampered.verify(&ProofVerifier::AcceptMock, &[]),
+            Err(Error::CorruptProof(_))
+        ));
+    }
+
+    /// Login proof answering a fresh ledger challenge
+    fn ledger_auth(
+        ledger: &mut Ledger,
+        prover: &ProverBackend,
+        account: &user::Account,
+        login_hash: [u8; 32],
+    ) -> sp1_sdk::SP1ProofWithPublicValues {
+        let challenge = ledger.issue_challenge(&account.username);
+        zk::generate_login_proof(prover, account, login_hash, &challenge, ProofMode::Groth16)
+            .unwrap()
+    }
+
+    #[test]
+    fn ledger() {
+        let client_pubkey = "test-client";
+
+        let dir = TestDir::new("ledger");
+        let config = dir.config();
+        let (public1, player1) = dir.player("player1");
+        let (public2, player2) = dir.player("player2");
+
+        let prover = ProverBackend::Mock;
+        let clock = Arc::new(ManualClock::new(1_000));
+
+        // By default the ledger checks every proof, mock proofs prove nothing
+        let mut strict = Ledger::new(client_pubkey).with_clock(clock.clone());
+        let proof = ledger_auth(&mut strict, &prover, &player1, public1.login_hash);
+        assert!(matches!(
+            strict.submit(Transaction::Register {
+                account: public1.clone(),
+                proof,
+            }),
+            Err(Error::Verification(_))
+        ));
+        assert!(strict.account("player1").is_none());
+
+        let mut ledger = Ledger::new(client_pubkey)
+            .with_verifier(ProofVerifier::AcceptMock)
+            .with_clock(clock.clone());
+
+        // Only proofs verifiable on-chain are taken
+        let challenge = ledger.issue_challenge("player1");
+        let proof = zk::generate_login_proof(
+            &prover,
+            &player1,
+            public1.login_hash,
+            &challenge,
+            ProofMode::Compressed,
+        )
+        .unwrap();
+        assert!(matches!(
+            ledger.submit(Transaction::Register {
+                account: public1.clone(),
+                proof,
+            }),
+            Err(Error::UnsupportedProofMode(ProofMode::Compressed))
+        ));
+
+        // Both players register by proving they know their secret
+        for (public, account) in [(&public1, &player1), (&public2, &player2)] {
+            let proof = ledger_auth(&mut ledger, &prover, account, public.login_hash);
+            ledger
+                .submit(Transaction::Register {
+                    account: public.clone(),
+                    proof,
+                })
+                .unwrap();
+        }
+
+        // Player 1 opens a game committed to rock, player 2 joins with paper
+        let create = |ledger: &mut Ledger, game_id: u64, open_timeout: u64| {
+            let choice_hash = rps_basic::generate_basic_choice_hash(
+                &config,
+                &player1,
+                client_pubkey,
+                game_id,
+                rps_basic::Choice::Rock as u8,
+            )
+            .unwrap();
+            let auth = ledger_auth(ledger, &prover, &player1, public1.login_hash);
+            ledger
+                .submit(Transaction::CreateGame {
+                    auth,
+                    game_id,
+                    choice_hash,
+                    commitment_version: rps_basic::CHOICE_COMMITMENT_VERSION,
+                    mode: rps_basic::GameMode::Plain,
+                    reveal_timeout: 60,
+                    open_timeout,
+                })
+                .unwrap();
+            choice_hash
+        };
+        let join = |ledger: &mut Ledger, game_id: u64| {
+            let auth = ledger_auth(ledger, &prover, &player2, public2.login_hash);
+            ledger.submit(Transaction::JoinGame {
+                auth,
+                game_id,
+                choice: JoinChoice::Plain(rps_basic::Choice::Paper),
+            })
+        };
+
+        let game_id = ledger.next_game_id();
+        let choice_hash = create(&mut ledger, game_id, 600);
+        join(&mut ledger, game_id).unwrap();
+
+        // A login proof authenticates a single transaction
+        let auth = ledger_auth(&mut ledger, &prover, &player1, public1.login_hash);
+        ledger
+            .submit(Transaction::CancelGame {
+                auth: auth.clone(),
+                game_id: 42,
+            })
+            .unwrap_err();
+        assert!(matches!(
+            ledger.submit(Transaction::CancelGame { auth, game_id }),
+            Err(Error::ChallengeReplayed)
+        ));
+
+        // Player 1 reveals with a rps-basic proof, the ledger settles the game
+        let commitment = new_commitment(client_pubkey, game_id, choice_hash);
+        let (proof, public_values, vk_hash) = rps_basic::generate_basic_game_proof(
+            &config,
+            &prover,
+            &player1,
+            &commitment,
+            ProofMode::Groth16,
+        )
+        .unwrap();
+
+        // The proof must be a SNARK checked against a vk the game accepts
+        assert!(matches!(
+            ledger.submit(Transaction::Reveal {
+                game_id,
+                mode: ProofMode::Core,
+                vk_hash: vk_hash.clone(),
+                proof: proof.clone(),
+                public_values: public_values.clone(),
+            }),
+            Err(Error::UnsupportedProofMode(ProofMode::Core))
+        ));
+        assert!(matches!(
+            ledger.submit(Transaction::Reveal {
+                game_id,
+                mode: ProofMode::Groth16,
+                vk_hash: "0xother".to_string(),
+                proof: proof.clone(),
+                public_values: public_values.clone(),
+            }),
+            Err(Error::GameTransition(
+                rps_basic::TransitionError::UnpinnedVk { .. }
+            ))
+        ));
+        let events = ledger
+            .submit(Transaction::Reveal {
+                game_id,
+                mode: ProofMode::Groth16,
+                vk_hash,
+                proof,
+                public_values,
+            })
+            .unwrap();
+        assert_eq!(
+            events.into_iter().map(|e| e.kind).collect::<Vec<_>>(),
+            vec![
+                EventKind::ChoiceRevealed {
+                    game_id,
+                    username: "player1".to_string(),
+                    choice: rps_basic::Choice::Rock,
+                },
+                EventKind::GameCompleted {
+                    game_id,
+                    result: GameResult::Player2,
+                },
+            ]
+        );
+        assert_eq!(
+            ledger.game(game_id).unwrap().result(),
+            Some(GameResult::Player2)
+        );
+
+        // Player 1 never reveals, player 2 claims the game once the deadline passed
+        let game_id = ledger.next_game_id();
+        create(&mut ledger, game_id, 600);
+        join(&mut ledger, game_id).unwrap();
+        clock.advance(61);
+        let auth = ledger_auth(&mut ledger, &prover, &player2, public2.login_hash);
+        ledger
+            .submit(Transaction::ClaimTimeout { auth, game_id })
+            .unwrap();
+        assert_eq!(
+            ledger.game(game_id).unwrap().result(),
+            Some(GameResult::Player2ByForfeit)
+        );
+
+        // Nobody joins in time, the next transaction expires the game
+        let game_id = ledger.next_game_id();
+        create(&mut ledger, game_id, 10);
+        clock.advance(11);
+        assert!(matches!(
+            join(&mut ledger, game_id),
+            Err(Error::GameTransition(_))
+        ));
+        assert_eq!(
+            ledger.events().last().unwrap().kind,
+            EventKind::GameExpired { game_id }
+        );
+
+        // The event log is ordered and numbered from 0
+        assert!(ledger
+            .events()
+            .iter()
+            .enumerate()
+            .all(|(seq, event)| event.seq == seq as u64));
+    }
 }
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use zk_games::games::rps_basic::{Choice, GameMode};
+use zk_games_types::GameResult;
+
+/// Something the ledger accepted, events are numbered in the order they happened
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LedgerEvent {
+    /// Position in the event log, starts at 0
+    pub seq: u64,
+    /// Ledger time, in unix seconds
+    pub at: u64,
+    pub kind: EventKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum EventKind {
+    AccountRegistered {
+        username: String,
+    },
+    /// The login hash of the account was replaced
+    AccountRotated {
+        username: String,
+    },
+    GameCreated {
+        game_id: u64,
+        player1: String,
+        mode: GameMode,
+    },
+    GameJoined {
+        game_id: u64,
+        player2: String,
+    },
+    ChoiceRevealed {
+        game_id: u64,
+        username: String,
+        choice: Choice,
+    },
+    GameCompleted {
+        game_id: u64,
+        result: GameResult,
+    },
+    /// A player claimed the game after the reveal deadline
+    GameForfeited {
+        game_id: u64,
+        result: GameResult,
+    },
+    GameCancelled {
+        game_id: u64,
+    },
+    GameExpired {
+        game_id: u64,
+    },
+}
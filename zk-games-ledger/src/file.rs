@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use zk_games::{json_file::JsonFile, Error, Result};
+
+use crate::{Ledger, LedgerState};
+
+/// A ledger kept in a JSON file, so every client process sees the same chain.
+/// The state is loaded before each transaction and written back once it is accepted.
+pub struct LedgerFile {
+    file: JsonFile,
+}
+
+impl LedgerFile {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let ledger_file = LedgerFile {
+            file: JsonFile::new(path, Error::CorruptLedger)?,
+        };
+
+        // Refuse to start on a corrupt ledger rather than overwrite it later
+        ledger_file.file.read::<LedgerState>()?;
+        Ok(ledger_file)
+    }
+
+    /// Latest state of the ledger
+    pub fn load(&self, ledger: &mut Ledger) -> Result<()> {
+        ledger.state = self.file.read()?;
+        Ok(())
+    }
+
+    /// Run `apply` on the latest state and write it back.
+    /// A rejected transaction leaves the state as it was, but still consumes its challenge.
+    pub fn transact<R>(
+        &self,
+        ledger: &mut Ledger,
+        apply: impl FnOnce(&mut Ledger) -> Result<R>,
+    ) -> Result<R> {
+        self.file.update(|state: &mut LedgerState| {
+            ledger.state = std::mem::take(state);
+            let res = apply(ledger);
+            *state = ledger.state.clone();
+            Ok(res)
+        })?
+    }
+}
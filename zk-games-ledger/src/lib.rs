@@ -0,0 +1,407 @@
+mod event;
+mod file;
+mod transaction;
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use sp1_sdk::SP1ProofWithPublicValues;
+use zk_games::{
+    clock::{Clock, SystemClock},
+    games::rps_basic::{
+        store::GameFilter, verify_basic_game_proof, Choice, ChoiceCommitment, CompletionProgram,
+        Game, PinnedVks, Player1Info, Player2Info,
+    },
+    login::{check_login_answer, LoginChallenge, LoginChallenges, LOGIN_CHALLENGE_TTL},
+    programs,
+    prover::ProofMode,
+    registry::PublicAccount,
+    Error, ProofVerifier, Result,
+};
+use zk_games_types::{LoginPublic, RotatePublic, RpsBasicPublic};
+
+pub use event::{EventKind, LedgerEvent};
+pub use file::LedgerFile;
+pub use transaction::{JoinChoice, Transaction};
+
+/// Proofs small enough to be checked on-chain, the only ones the ledger takes
+pub const ACCEPTED_PROOF_MODES: [ProofMode; 2] = [ProofMode::Groth16, ProofMode::Plonk];
+
+fn check_proof_mode(mode: ProofMode) -> Result<()> {
+    if !ACCEPTED_PROOF_MODES.contains(&mode) {
+        return Err(Error::UnsupportedProofMode(mode));
+    }
+    Ok(())
+}
+
+/// Everything the ledger agreed on so far
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct LedgerState {
+    accounts: BTreeMap<String, PublicAccount>,
+    games: BTreeMap<u64, Game>,
+    challenges: LoginChallenges,
+    events: Vec<LedgerEvent>,
+}
+
+/// The authoritative registry and game state, it stands for the chain.
+/// Only transactions backed by valid proofs change it, every change is logged as an event.
+pub struct Ledger {
+    /// Strict unless a test swaps it
+    verifier: ProofVerifier,
+    /// Only proofs meant for this client are accepted
+    client_pubkey: String,
+    clock: Arc<dyn Clock>,
+    challenge_ttl: u64,
+    /// vk digests accepted on top of the ones pinned by each game
+    accepted_vks: Vec<String>,
+    state: LedgerState,
+}
+
+impl Ledger {
+    pub fn new(client_pubkey: &str) -> Self {
+        Ledger {
+            verifier: ProofVerifier::Strict,
+            client_pubkey: client_pubkey.to_string(),
+            clock: Arc::new(SystemClock),
+            challenge_ttl: LOGIN_CHALLENGE_TTL,
+            accepted_vks: vec![],
+            state: LedgerState::default(),
+        }
+    }
+
+    /// Only for tests running the mock prover
+    pub fn with_verifier(mut self, verifier: ProofVerifier) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_challenge_ttl(mut self, ttl: u64) -> Self {
+        self.challenge_ttl = ttl;
+        self
+    }
+
+    pub fn with_accepted_vks(mut self, accepted_vks: Vec<String>) -> Self {
+        self.accepted_vks = accepted_vks;
+        self
+    }
+
+    pub fn with_state(mut self, state: LedgerState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn state(&self) -> &LedgerState {
+        &self.state
+    }
+
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    pub fn client_pubkey(&self) -> &str {
+        &self.client_pubkey
+    }
+
+    pub fn account(&self, username: &str) -> Option<&PublicAccount> {
+        self.state.accounts.get(username)
+    }
+
+    pub fn game(&self, id: u64) -> Option<&Game> {
+        self.state.games.get(&id)
+    }
+
+    /// Every game matching the filter, by id
+    pub fn games(&self, filter: &GameFilter) -> Vec<&Game> {
+        self.state
+            .games
+            .values()
+            .filter(|game| filter.matches(game))
+            .collect()
+    }
+
+    /// Id following every game, commitments are bound to the id so it is picked before creating
+    pub fn next_game_id(&self) -> u64 {
+        self.state
+            .games
+            .last_key_value()
+            .map_or(0, |(id, _)| id + 1)
+    }
+
+    /// The whole event log, in order
+    pub fn events(&self) -> &[LedgerEvent] {
+        &self.state.events
+    }
+
+    /// Challenge to answer with a login proof, it authenticates a single transaction
+    pub fn issue_challenge(&mut self, username: &str) -> LoginChallenge {
+        let now = self.now();
+        self.state
+            .challenges
+            .issue(username, &self.client_pubkey, now + self.challenge_ttl, now)
+    }
+
+    /// Apply a transaction, returns the events it produced.
+    /// Games nobody joined in time expire first, a rejected transaction only consumes its challenge.
+    pub fn submit(&mut self, transaction: Transaction) -> Result<Vec<LedgerEvent>> {
+        let first = self.state.events.len();
+
+        self.expire_games();
+        self.apply(transaction)?;
+
+        Ok(self.state.events[first..].to_vec())
+    }
+
+    fn emit(&mut self, kind: EventKind) {
+        let event = LedgerEvent {
+            seq: self.state.events.len() as u64,
+            at: self.now(),
+            kind,
+        };
+        self.state.events.push(event);
+    }
+
+    /// Expire every open game nobody joined in time, anyone can do it.
+    /// Returns the events, transactions already do it before applying.
+    pub fn expire_games(&mut self) -> Vec<LedgerEvent> {
+        let first = self.state.events.len();
+        let now = self.now();
+        let expired = self
+            .state
+            .games
+            .values_mut()
+            .filter_map(|game| game.expire(now).ok().map(|_| game.id))
+            .collect::<Vec<_>>();
+
+        for game_id in expired {
+            self.emit(EventKind::GameExpired { game_id });
+        }
+        self.state.events[first..].to_vec()
+    }
+
+    /// Consume the challenge a login proof answers, whatever the outcome of the transaction
+    fn take_challenge(
+        &mut self,
+        proof: &SP1ProofWithPublicValues,
+    ) -> Result<(LoginChallenge, LoginPublic)> {
+        let public_values = LoginPublic::try_from(proof.public_values.as_slice())
+            .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+
+        let now = self.now();
+        let challenge = self
+            .state
+            .challenges
+            .take(&public_values.random_string, now)?;
+        check_proof_mode(ProofMode::of(proof))?;
+
+        Ok((challenge, public_values))
+    }
+
+    /// Registered player behind a login proof
+    fn authenticate(&mut self, proof: &SP1ProofWithPublicValues) -> Result<String> {
+        let (challenge, public_values) = self.take_challenge(proof)?;
+        let account = self
+            .account(&challenge.username)
+            .ok_or_else(|| Error::AccountNotFound(challenge.username.clone()))?;
+
+        check_login_answer(
+            &self.verifier,
+            proof,
+            public_values,
+            &challenge,
+            account.login_hash,
+            self.now(),
+        )?;
+        Ok(challenge.username)
+    }
+
+    fn game_cloned(&self, id: u64) -> Result<Game> {
+        self.game(id).cloned().ok_or(Error::GameNotFound(id))
+    }
+
+    fn apply(&mut self, transaction: Transaction) -> Result<()> {
+        let now = self.now();
+
+        match transaction {
+            Transaction::Register { account, proof } => {
+                if self.state.accounts.contains_key(&account.username) {
+                    return Err(Error::AccountExists(account.username));
+                }
+
+                let (challenge, public_values) = self.take_challenge(&proof)?;
+                if challenge.username != account.username {
+                    return Err(Error::InvalidPublicValues(format!(
+                        "registration proof is for {}, expected {}",
+                        challenge.username, account.username
+                    )));
+                }
+                check_login_answer(
+                    &self.verifier,
+                    &proof,
+                    public_values,
+                    &challenge,
+                    account.login_hash,
+                    now,
+                )?;
+
+                let username = account.username.clone();
+                self.state.accounts.insert(username.clone(), account);
+                self.emit(EventKind::AccountRegistered { username });
+            }
+            Transaction::RotateAccount { account, proof } => {
+                let current = self
+                    .account(&account.username)
+                    .ok_or_else(|| Error::AccountNotFound(account.username.clone()))?;
+
+                check_proof_mode(ProofMode::of(&proof))?;
+                self.verifier.verify(&proof, &programs::ROTATE)?;
+                let public_values = RotatePublic::try_from(proof.public_values.as_slice())
+                    .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+                if public_values.username != account.username
+                    || public_values.old_login_hash != current.login_hash
+                    || public_values.new_login_hash != account.login_hash
+                    || public_values.new_kdf_hash != account.kdf.hash()
+                {
+                    return Err(Error::InvalidPublicValues(format!(
+                        "rotation proof doesn't match the account of {}",
+                        account.username
+                    )));
+                }
+
+                let username = account.username.clone();
+                self.state.accounts.insert(username.clone(), account);
+                self.emit(EventKind::AccountRotated { username });
+            }
+            Transaction::CreateGame {
+                auth,
+                game_id,
+                choice_hash,
+                commitment_version,
+                mode,
+                reveal_timeout,
+                open_timeout,
+            } => {
+                let username = self.authenticate(&auth)?;
+                if self.state.games.contains_key(&game_id) {
+                    return Err(Error::GameExists(game_id));
+                }
+
+                let game = Game::new(
+                    game_id,
+                    Player1Info {
+                        username: username.clone(),
+                        choice_hash,
+                        commitment_version,
+                    },
+                    reveal_timeout,
+                )
+                .with_mode(mode)
+                .with_open_until(now + open_timeout)
+                .with_vks(PinnedVks::current());
+
+                self.state.games.insert(game_id, game);
+                self.emit(EventKind::GameCreated {
+                    game_id,
+                    player1: username,
+                    mode,
+                });
+            }
+            Transaction::JoinGame {
+                auth,
+                game_id,
+                choice,
+            } => {
+                let username = self.authenticate(&auth)?;
+                let mut game = self.game_cloned(game_id)?;
+
+                game.join(
+                    Player2Info {
+                        username: username.clone(),
+                        choice: choice.into(),
+                    },
+                    now,
+                )?;
+
+                self.state.games.insert(game_id, game);
+                self.emit(EventKind::GameJoined {
+                    game_id,
+                    player2: username,
+                });
+            }
+            Transaction::Reveal {
+                game_id,
+                mode,
+                vk_hash,
+                proof,
+                public_values,
+            } => {
+                check_proof_mode(mode)?;
+                let mut game = self.game_cloned(game_id)?;
+
+                // The proof speaks for the player whose commitment it opens
+                let revealed = RpsBasicPublic::try_from(public_values.as_slice())
+                    .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+                let (username, commitment) = std::iter::once(game.player1.username.clone())
+                    .chain(game.player2().map(|player2| player2.username.clone()))
+                    .find_map(|username| {
+                        ChoiceCommitment::of(&game, &username, &self.client_pubkey)
+                            .filter(|commitment| commitment.choice_hash == revealed.choice_hash)
+                            .map(|commitment| (username, commitment))
+                    })
+                    .ok_or_else(|| {
+                        Error::InvalidPublicValues(format!(
+                            "reveal doesn't open a commitment of game {game_id}"
+                        ))
+                    })?;
+
+                game.check_reveal(&username, now)?;
+                // Any build the game accepts, the proof is checked against the same vk
+                game.check_vk(CompletionProgram::Reveal, &vk_hash, &self.accepted_vks)?;
+                let verified = verify_basic_game_proof(
+                    &self.verifier,
+                    mode,
+                    &vk_hash,
+                    &proof,
+                    &public_values,
+                    &commitment,
+                )?;
+                let choice = Choice::try_from(verified.choice).map_err(|_| {
+                    Error::InvalidPublicValues(format!("invalid choice {}", verified.choice))
+                })?;
+
+                let result = game.reveal(&username, choice, now)?;
+                self.state.games.insert(game_id, game);
+                self.emit(EventKind::ChoiceRevealed {
+                    game_id,
+                    username,
+                    choice,
+                });
+                if let Some(result) = result {
+                    self.emit(EventKind::GameCompleted { game_id, result });
+                }
+            }
+            Transaction::ClaimTimeout { auth, game_id } => {
+                let username = self.authenticate(&auth)?;
+                let mut game = self.game_cloned(game_id)?;
+
+                let result = game.claim_timeout(&username, now)?;
+                self.state.games.insert(game_id, game);
+                self.emit(EventKind::GameForfeited { game_id, result });
+            }
+            Transaction::CancelGame { auth, game_id } => {
+                let username = self.authenticate(&auth)?;
+                let mut game = self.game_cloned(game_id)?;
+
+                game.cancel(&username)?;
+                self.state.games.insert(game_id, game);
+                self.emit(EventKind::GameCancelled { game_id });
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use sp1_sdk::SP1ProofWithPublicValues;
+use zk_games::{
+    games::rps_basic::{Choice, GameMode, Player2Choice},
+    prover::ProofMode,
+    registry::PublicAccount,
+};
+use zk_games_types::commitment::CommitmentVersion;
+
+/// A change submitted to the ledger, each one carries the proof that allows it.
+/// `auth` is a login proof answering a challenge issued by the ledger,
+/// the sender is the player it logs in.
+pub enum Transaction {
+    /// The login proof shows the sender knows the secret behind the new login hash
+    Register {
+        account: PublicAccount,
+        proof: SP1ProofWithPublicValues,
+    },
+    /// Replace the login hash, with a rotate proof of the secret behind the registered one
+    RotateAccount {
+        account: PublicAccount,
+        proof: SP1ProofWithPublicValues,
+    },
+    /// Open a game committed to the choice of the sender, the id is part of the commitment
+    CreateGame {
+        auth: SP1ProofWithPublicValues,
+        game_id: u64,
+        choice_hash: [u8; 32],
+        commitment_version: CommitmentVersion,
+        mode: GameMode,
+        /// Seconds to reveal once player 2 joined
+        reveal_timeout: u64,
+        /// Seconds the game waits for a player 2
+        open_timeout: u64,
+    },
+    JoinGame {
+        auth: SP1ProofWithPublicValues,
+        game_id: u64,
+        choice: JoinChoice,
+    },
+    /// A rps-basic proof, the revealing player is the one whose commitment it opens
+    Reveal {
+        game_id: u64,
+        mode: ProofMode,
+        /// vk the proof was generated with, the game must accept it
+        vk_hash: String,
+        proof: Vec<u8>,
+        public_values: Vec<u8>,
+    },
+    ClaimTimeout {
+        auth: SP1ProofWithPublicValues,
+        game_id: u64,
+    },
+    CancelGame {
+        auth: SP1ProofWithPublicValues,
+        game_id: u64,
+    },
+}
+
+/// How player 2 joins, it must match the mode of the game
+#[derive(Clone, Debug, PartialEq)]
+pub enum JoinChoice {
+    Plain(Choice),
+    Hidden {
+        choice_hash: [u8; 32],
+        commitment_version: CommitmentVersion,
+    },
+}
+
+impl From<JoinChoice> for Player2Choice {
+    fn from(choice: JoinChoice) -> Self {
+        match choice {
+            JoinChoice::Plain(choice) => Player2Choice::Plain(choice),
+            JoinChoice::Hidden {
+                choice_hash,
+                commitment_version,
+            } => Player2Choice::Hidden {
+                choice_hash,
+                commitment_version,
+                revealed: None,
+            },
+        }
+    }
+}
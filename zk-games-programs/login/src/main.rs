@@ -13,10 +13,13 @@ fn main() {
     // Confirm the login hash with the provided hash
     assert_eq!(login_hash, login_input.login_hash);
 
-    // Commit the login result to confirm the
-    // right login hash and random string were used
+    // Commit the login result to confirm the right login hash and random string
+    // were used, for which account, for which client and until when
     sp1_zkvm::io::commit(&LoginPublic {
+        username: login_input.username,
         login_hash,
         random_string: login_input.random_string,
+        client_pubkey: login_input.client_pubkey,
+        expires_at: login_input.expires_at,
     });
 }
@@ -0,0 +1,24 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use sha2::{Digest, Sha256};
+use zk_games_types::{RotateInput, RotatePublic};
+
+fn main() {
+    let rotate_input = sp1_zkvm::io::read::<RotateInput>();
+
+    // Create the login hash from the old secret
+    let old_login_hash: [u8; 32] = Sha256::digest(&rotate_input.old_secret).into();
+
+    // Confirm we know the secret of the registered login hash
+    assert_eq!(old_login_hash, rotate_input.old_login_hash);
+
+    // Commit the rotation so the registry can replace
+    // the old login hash and kdf with the new ones
+    sp1_zkvm::io::commit(&RotatePublic {
+        username: rotate_input.username,
+        old_login_hash,
+        new_login_hash: rotate_input.new_login_hash,
+        new_kdf_hash: rotate_input.new_kdf_hash,
+    });
+}
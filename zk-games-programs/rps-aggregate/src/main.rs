@@ -0,0 +1,46 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use sha2::{Digest, Sha256};
+use zk_games_types::{RpsAggregateInput, RpsAggregatePublic, RpsBasicPublic, RpsBasicReveal};
+
+fn main() {
+    let rps_aggregate_input = sp1_zkvm::io::read::<RpsAggregateInput>();
+    assert!(
+        !rps_aggregate_input.public_values.is_empty(),
+        "No reveal to aggregate"
+    );
+
+    let mut client_pubkey = None;
+    let mut reveals = Vec::with_capacity(rps_aggregate_input.public_values.len());
+
+    for public_values in rps_aggregate_input.public_values {
+        // Verify the compressed rps-basic proof behind these public values
+        let public_values_digest: [u8; 32] = Sha256::digest(&public_values).into();
+        sp1_zkvm::lib::verify::verify_sp1_proof(
+            &rps_aggregate_input.rps_basic_vkey,
+            &public_values_digest,
+        );
+
+        let rps_basic_public =
+            RpsBasicPublic::try_from(public_values.as_slice()).expect("Invalid public values");
+
+        // Every reveal must come from the same client
+        let expected_client_pubkey =
+            client_pubkey.get_or_insert_with(|| rps_basic_public.client_pubkey.clone());
+        assert_eq!(*expected_client_pubkey, rps_basic_public.client_pubkey);
+
+        reveals.push(RpsBasicReveal {
+            game_id: rps_basic_public.game_id,
+            choice_hash: rps_basic_public.choice_hash,
+            choice: rps_basic_public.choice,
+        });
+    }
+
+    // Commit the vkey so the verifier knows which program revealed the choices
+    sp1_zkvm::io::commit(&RpsAggregatePublic {
+        rps_basic_vkey: rps_aggregate_input.rps_basic_vkey,
+        client_pubkey: client_pubkey.expect("No reveal to aggregate"),
+        reveals,
+    });
+}
@@ -1,33 +1,31 @@
 #![no_main]
 sp1_zkvm::entrypoint!(main);
 
-use sha2::{Digest, Sha256};
-use zk_games_types::{RpsBasicInput, RpsBasicPublic};
+use zk_games_types::{commitment::choice_commitment, RpsBasicInput, RpsBasicPublic};
 
 fn main() {
     let rps_basic_input = sp1_zkvm::io::read::<RpsBasicInput>();
 
-    // We brute force the choice by creating a hash that matches
-    for choice in 0..3 {
-        let choice_hash = Sha256::new()
-            .chain_update(rps_basic_input.secret)
-            .chain_update(rps_basic_input.client_pubkey.clone())
-            .chain_update(rps_basic_input.game_id.to_string())
-            .chain_update(choice.to_string())
-            .finalize()
-            .into();
+    // The choice must be a rock, a paper or a scissors
+    assert!(rps_basic_input.choice < 3, "Invalid choice");
 
-        if choice_hash == rps_basic_input.choice_hash {
-            // We found the right choice
-            sp1_zkvm::io::commit(&RpsBasicPublic {
-                client_pubkey: rps_basic_input.client_pubkey,
-                game_id: rps_basic_input.game_id,
-                choice_hash,
-                choice,
-            });
-            return;
-        }
-    }
+    // Confirm the choice is the one behind the choice hash
+    println!("cycle-tracker-start: choice-commitment");
+    let choice_hash = choice_commitment(
+        rps_basic_input.version,
+        &rps_basic_input.secret,
+        &rps_basic_input.client_pubkey,
+        rps_basic_input.game_id,
+        rps_basic_input.choice,
+        &rps_basic_input.nonce,
+    );
+    println!("cycle-tracker-end: choice-commitment");
+    assert_eq!(choice_hash, rps_basic_input.choice_hash);
 
-    panic!("No matching choice found");
+    sp1_zkvm::io::commit(&RpsBasicPublic {
+        client_pubkey: rps_basic_input.client_pubkey,
+        game_id: rps_basic_input.game_id,
+        choice_hash,
+        choice: rps_basic_input.choice,
+    });
 }
@@ -0,0 +1,40 @@
+#![no_main]
+sp1_zkvm::entrypoint!(main);
+
+use zk_games_types::{
+    calculate_result, commitment::choice_commitment, RpsResultInput, RpsResultPublic,
+};
+
+fn main() {
+    let rps_result_input = sp1_zkvm::io::read::<RpsResultInput>();
+
+    // Both choices must be a rock, a paper or a scissors
+    assert!(rps_result_input.choice < 3, "Invalid player1 choice");
+    assert!(
+        rps_result_input.player2_choice < 3,
+        "Invalid player2 choice"
+    );
+
+    // Confirm the private choice is the one behind the choice hash
+    let choice_hash = choice_commitment(
+        rps_result_input.version,
+        &rps_result_input.secret,
+        &rps_result_input.client_pubkey,
+        rps_result_input.game_id,
+        rps_result_input.choice,
+        &rps_result_input.nonce,
+    );
+    assert_eq!(choice_hash, rps_result_input.choice_hash);
+
+    // The winner is decided here, nobody has to trust the host with it
+    let result = calculate_result(rps_result_input.choice, rps_result_input.player2_choice);
+
+    sp1_zkvm::io::commit(&RpsResultPublic {
+        client_pubkey: rps_result_input.client_pubkey,
+        game_id: rps_result_input.game_id,
+        choice_hash,
+        player1_choice: rps_result_input.choice,
+        player2_choice: rps_result_input.player2_choice,
+        result,
+    });
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Domain separation tag of choice commitments
+pub const CHOICE_COMMITMENT_DST: &[u8] = b"zk-games/choice-commitment";
+
+/// How a choice hash was built, old commitments stay valid under V1
+#[repr(u8)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CommitmentVersion {
+    /// `sha256(secret || client_pubkey || game_id || choice)` with decimal strings
+    #[default]
+    V1 = 1,
+    /// Domain separated, fixed width fields and a per game blinding nonce
+    V2 = 2,
+}
+
+/// Build the choice hash, the nonce is ignored by V1
+pub fn choice_commitment(
+    version: CommitmentVersion,
+    secret: &[u8; 32],
+    client_pubkey: &str,
+    game_id: u64,
+    choice: u8,
+    nonce: &[u8; 32],
+) -> [u8; 32] {
+    match version {
+        CommitmentVersion::V1 => choice_commitment_v1(secret, client_pubkey, game_id, choice),
+        CommitmentVersion::V2 => {
+            choice_commitment_v2(secret, client_pubkey, game_id, choice, nonce)
+        }
+    }
+}
+
+/// Legacy encoding, kept to accept commitments made before V2
+pub fn choice_commitment_v1(
+    secret: &[u8; 32],
+    client_pubkey: &str,
+    game_id: u64,
+    choice: u8,
+) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(secret)
+        .chain_update(client_pubkey)
+        .chain_update(game_id.to_string())
+        .chain_update(choice.to_string())
+        .finalize()
+        .into()
+}
+
+/// `sha256(version || dst || secret || sha256(client_pubkey) || game_id_be || choice || nonce)`
+pub fn choice_commitment_v2(
+    secret: &[u8; 32],
+    client_pubkey: &str,
+    game_id: u64,
+    choice: u8,
+    nonce: &[u8; 32],
+) -> [u8; 32] {
+    // The pubkey is the only variable length field, we hash it to get a fixed width
+    let client_pubkey_hash = Sha256::digest(client_pubkey);
+
+    Sha256::new()
+        .chain_update([CommitmentVersion::V2 as u8])
+        .chain_update(CHOICE_COMMITMENT_DST)
+        .chain_update(secret)
+        .chain_update(client_pubkey_hash)
+        .chain_update(game_id.to_be_bytes())
+        .chain_update([choice])
+        .chain_update(nonce)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{choice_commitment, CommitmentVersion};
+
+    const SECRET: [u8; 32] = [7; 32];
+    const CLIENT: &str = "client";
+
+    #[test]
+    fn test_v1_ignores_nonce() {
+        assert_eq!(
+            choice_commitment(CommitmentVersion::V1, &SECRET, CLIENT, 1, 0, &[1; 32]),
+            choice_commitment(CommitmentVersion::V1, &SECRET, CLIENT, 1, 0, &[2; 32])
+        );
+    }
+
+    #[test]
+    fn test_v2_is_blinded_and_unambiguous() {
+        let commitment = choice_commitment(CommitmentVersion::V2, &SECRET, CLIENT, 1, 0, &[1; 32]);
+
+        // Another nonce gives another commitment
+        assert_ne!(
+            commitment,
+            choice_commitment(CommitmentVersion::V2, &SECRET, CLIENT, 1, 0, &[2; 32])
+        );
+        // Never equal to the V1 commitment of the same choice
+        assert_ne!(
+            commitment,
+            choice_commitment(CommitmentVersion::V1, &SECRET, CLIENT, 1, 0, &[1; 32])
+        );
+        // "game 11, choice 1" and "game 1, choice 11" collide in V1 but not in V2
+        assert_eq!(
+            choice_commitment(CommitmentVersion::V1, &SECRET, CLIENT, 11, 1, &[0; 32]),
+            choice_commitment(CommitmentVersion::V1, &SECRET, CLIENT, 1, 11, &[0; 32])
+        );
+        assert_ne!(
+            choice_commitment(CommitmentVersion::V2, &SECRET, CLIENT, 11, 1, &[0; 32]),
+            choice_commitment(CommitmentVersion::V2, &SECRET, CLIENT, 1, 11, &[0; 32])
+        );
+    }
+}
@@ -1,3 +1,6 @@
+pub mod commitment;
+
+use commitment::CommitmentVersion;
 use serde::{Deserialize, Serialize};
 
 /// Once the game is finished, we set who the winner is
@@ -6,16 +9,35 @@ pub enum GameResult {
     Player1,
     Player2,
     Draw,
+    /// Player 2 never revealed his hidden choice, player 1 wins without playing it out
+    Player1ByForfeit,
+    /// Player 1 never revealed his choice, player 2 wins without playing it out
+    Player2ByForfeit,
+}
+
+/// Rock paper scissors rules, choices are `0` rock, `1` paper and `2` scissors
+pub fn calculate_result(choice_1: u8, choice_2: u8) -> GameResult {
+    match (choice_1, choice_2) {
+        (1, 0) | (2, 1) | (0, 2) => GameResult::Player1,
+        (0, 1) | (1, 2) | (2, 0) => GameResult::Player2,
+        _ => GameResult::Draw,
+    }
 }
 
 /// Struct to provide as input values to the ZK login program
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LoginInput {
+    /// The account we log in to
+    pub username: String,
     /// The login hash we try to prove
     pub login_hash: [u8; 32],
     /// A random string provided by the game client
     /// to confirm login attempt
     pub random_string: String,
+    /// The client the proof is meant for
+    pub client_pubkey: String,
+    /// Unix timestamp after which the proof is not valid
+    pub expires_at: u64,
     /// The secret to prove with
     pub secret: [u8; 32],
 }
@@ -23,8 +45,51 @@ pub struct LoginInput {
 /// login result we get after verifying the proof
 #[derive(Serialize, Deserialize, Debug)]
 pub struct LoginPublic {
+    pub username: String,
     pub login_hash: [u8; 32],
     pub random_string: String,
+    pub client_pubkey: String,
+    pub expires_at: u64,
+}
+
+impl TryFrom<&[u8]> for LoginPublic {
+    type Error = bincode::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bincode::deserialize::<LoginPublic>(bytes)
+    }
+}
+
+/// Struct to provide as input values to the ZK rotate program
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RotateInput {
+    /// The account we rotate the secret of
+    pub username: String,
+    /// The currently registered login hash
+    pub old_login_hash: [u8; 32],
+    /// The login hash of the new secret
+    pub new_login_hash: [u8; 32],
+    /// Hash of how the new secret is derived, the registry publishes it with the login hash
+    pub new_kdf_hash: [u8; 32],
+    /// The old secret to prove with
+    pub old_secret: [u8; 32],
+}
+
+/// rotate result we get after verifying the proof
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RotatePublic {
+    pub username: String,
+    pub old_login_hash: [u8; 32],
+    pub new_login_hash: [u8; 32],
+    pub new_kdf_hash: [u8; 32],
+}
+
+impl TryFrom<&[u8]> for RotatePublic {
+    type Error = bincode::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bincode::deserialize::<RotatePublic>(bytes)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -35,6 +100,12 @@ pub struct RpsBasicInput {
     pub game_id: u64,
     /// The choice hash we try to prove
     pub choice_hash: [u8; 32],
+    /// How the choice hash was built
+    pub version: CommitmentVersion,
+    /// Blinding nonce of the choice hash, unused by V1
+    pub nonce: [u8; 32],
+    /// The choice behind the choice hash
+    pub choice: u8,
     /// The secret to prove with
     pub secret: [u8; 32],
 }
@@ -49,14 +120,117 @@ pub struct RpsBasicPublic {
     pub choice: u8,
 }
 
-impl From<Vec<u8>> for RpsBasicPublic {
-    fn from(bytes: Vec<u8>) -> Self {
-        bincode::deserialize::<RpsBasicPublic>(&bytes).unwrap()
+impl TryFrom<&[u8]> for RpsBasicPublic {
+    type Error = bincode::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bincode::deserialize::<RpsBasicPublic>(bytes)
     }
 }
 
-impl Into<Vec<u8>> for RpsBasicPublic {
-    fn into(self) -> Vec<u8> {
-        bincode::serialize(&self).unwrap()
+impl From<RpsBasicPublic> for Vec<u8> {
+    fn from(public_values: RpsBasicPublic) -> Self {
+        bincode::serialize(&public_values).unwrap()
+    }
+}
+
+/// Input of the aggregation program, the compressed proofs are written next to it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpsAggregateInput {
+    /// vkey hash of the rps-basic program every proof must come from
+    pub rps_basic_vkey: [u32; 8],
+    /// Public values of each rps-basic proof, in the order the proofs are written
+    pub public_values: Vec<Vec<u8>>,
+}
+
+/// One choice revealed by an aggregated rps-basic proof
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RpsBasicReveal {
+    pub game_id: u64,
+    #[serde(with = "serde_bytes")]
+    pub choice_hash: [u8; 32],
+    pub choice: u8,
+}
+
+/// Every choice revealed by the aggregated proofs
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpsAggregatePublic {
+    pub rps_basic_vkey: [u32; 8],
+    pub client_pubkey: String,
+    pub reveals: Vec<RpsBasicReveal>,
+}
+
+impl TryFrom<&[u8]> for RpsAggregatePublic {
+    type Error = bincode::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bincode::deserialize::<RpsAggregatePublic>(bytes)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpsResultInput {
+    /// Client ID
+    pub client_pubkey: String,
+    /// Game ID
+    pub game_id: u64,
+    /// The choice hash of player 1
+    pub choice_hash: [u8; 32],
+    /// How the choice hash was built
+    pub version: CommitmentVersion,
+    /// Blinding nonce of the choice hash, unused by V1
+    pub nonce: [u8; 32],
+    /// The choice of player 1, stays private
+    pub choice: u8,
+    /// The public choice of player 2
+    pub player2_choice: u8,
+    /// The secret to prove with
+    pub secret: [u8; 32],
+}
+
+/// Game result we get after verifying the proof, computed inside the program
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RpsResultPublic {
+    pub client_pubkey: String,
+    pub game_id: u64,
+    #[serde(with = "serde_bytes")]
+    pub choice_hash: [u8; 32],
+    pub player1_choice: u8,
+    pub player2_choice: u8,
+    pub result: GameResult,
+}
+
+impl TryFrom<&[u8]> for RpsResultPublic {
+    type Error = bincode::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bincode::deserialize::<RpsResultPublic>(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{calculate_result, GameResult};
+
+    const ROCK: u8 = 0;
+    const PAPER: u8 = 1;
+    const SCISSORS: u8 = 2;
+
+    #[test]
+    fn test_results() {
+        // Test the player 1 wins when it should
+        assert_eq!(calculate_result(ROCK, SCISSORS), GameResult::Player1);
+        assert_eq!(calculate_result(PAPER, ROCK), GameResult::Player1);
+        assert_eq!(calculate_result(SCISSORS, PAPER), GameResult::Player1);
+
+        // Test player 2 wins when it should
+        assert_eq!(calculate_result(SCISSORS, ROCK), GameResult::Player2);
+        assert_eq!(calculate_result(ROCK, PAPER), GameResult::Player2);
+        assert_eq!(calculate_result(PAPER, SCISSORS), GameResult::Player2);
+
+        // Test draw
+        assert_eq!(calculate_result(ROCK, ROCK), GameResult::Draw);
+        assert_eq!(calculate_result(PAPER, PAPER), GameResult::Draw);
+        assert_eq!(calculate_result(SCISSORS, SCISSORS), GameResult::Draw);
     }
 }
@@ -0,0 +1,31 @@
+use std::path::Path;
+
+use sp1_build::BuildArgs;
+
+/// Guest programs, by directory in zk-games-programs
+const PROGRAMS: [&str; 5] = [
+    "login",
+    "rotate",
+    "rps-basic",
+    "rps-result",
+    "rps-aggregate",
+];
+
+fn main() {
+    let programs_dir = Path::new(&std::env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("..")
+        .join("zk-games-programs");
+
+    for dir in PROGRAMS {
+        let program_dir = programs_dir.join(dir);
+        sp1_build::build_program_with_args(
+            program_dir.to_str().unwrap(),
+            BuildArgs {
+                // The local SP1 toolchain is enough, docker gives reproducible ELFs
+                docker: cfg!(feature = "docker"),
+                output_directory: Some(program_dir.join("elf").to_str().unwrap().to_string()),
+                ..Default::default()
+            },
+        );
+    }
+}
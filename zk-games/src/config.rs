@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+/// Env var pointing to a JSON config file
+pub const CONFIG_FILE_ENV: &str = "ZK_GAMES_CONFIG";
+/// Env var pointing to a data root, all paths are derived from it
+pub const DATA_DIR_ENV: &str = "ZK_GAMES_DATA_DIR";
+/// Data root used when nothing else is configured, relative to the current dir
+pub const DEFAULT_DATA_DIR: &str = "data";
+
+pub const LOCAL_PLAYERS_DIRNAME: &str = "local-players";
+pub const PUBLIC_PLAYERS_DIRNAME: &str = "public-players";
+pub const PROOFS_DIRNAME: &str = "proofs";
+pub const GAMES_FILENAME: &str = "games.json";
+
+/// Where every zk-games crate stores its data
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Private player data (secrets), never shared
+    pub local_players_dir: PathBuf,
+    /// Public player registry (login hashes)
+    pub public_players_dir: PathBuf,
+    /// Generated proofs
+    pub proofs_dir: PathBuf,
+    /// Games list, a `.db`, `.sqlite` or `.sqlite3` file is a SQLite database, anything else JSON
+    pub games_file: PathBuf,
+    /// vk digests accepted on top of the ones pinned by each game, for planned upgrades
+    #[serde(default)]
+    pub accepted_vks: Vec<String>,
+    /// Ledger standing for the chain, when set the client plays through it, not the games file
+    #[serde(default)]
+    pub ledger_file: Option<PathBuf>,
+}
+
+impl Config {
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder::default()
+    }
+
+    /// Resolve the config in this order:
+    /// 1. config file from `ZK_GAMES_CONFIG`
+    /// 2. data root from `ZK_GAMES_DATA_DIR`
+    /// 3. `./data`
+    pub fn load() -> Result<Config> {
+        if let Ok(path) = std::env::var(CONFIG_FILE_ENV) {
+            return Config::from_file(path);
+        }
+
+        let data_dir = std::env::var(DATA_DIR_ENV).unwrap_or_else(|_| DEFAULT_DATA_DIR.to_string());
+        Ok(Config::from_data_dir(data_dir))
+    }
+
+    /// Every path is placed under the given data root
+    pub fn from_data_dir(data_dir: impl Into<PathBuf>) -> Config {
+        Config::builder().data_dir(data_dir).build()
+    }
+
+    /// Read a JSON config file, relative paths are resolved from the file directory
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .map_err(|e| Error::Config(format!("failed to open {}: {e}", path.display())))?;
+        let builder: ConfigBuilder = serde_json::from_reader(file)
+            .map_err(|e| Error::Config(format!("failed to parse {}: {e}", path.display())))?;
+
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Ok(builder.relative_to(base_dir).build())
+    }
+}
+
+/// Builds a [`Config`], every path not set is derived from the data root
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct ConfigBuilder {
+    data_dir: Option<PathBuf>,
+    local_players_dir: Option<PathBuf>,
+    public_players_dir: Option<PathBuf>,
+    proofs_dir: Option<PathBuf>,
+    games_file: Option<PathBuf>,
+    #[serde(default)]
+    accepted_vks: Vec<String>,
+    ledger_file: Option<PathBuf>,
+}
+
+impl ConfigBuilder {
+    pub fn data_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.data_dir = Some(path.into());
+        self
+    }
+
+    pub fn local_players_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.local_players_dir = Some(path.into());
+        self
+    }
+
+    pub fn public_players_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.public_players_dir = Some(path.into());
+        self
+    }
+
+    pub fn proofs_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.proofs_dir = Some(path.into());
+        self
+    }
+
+    pub fn games_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.games_file = Some(path.into());
+        self
+    }
+
+    pub fn ledger_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ledger_file = Some(path.into());
+        self
+    }
+
+    /// Accept proofs of this vk digest in every game, see [`Config::accepted_vks`]
+    pub fn accepted_vk(mut self, vk_hash: impl Into<String>) -> Self {
+        self.accepted_vks.push(vk_hash.into());
+        self
+    }
+
+    pub fn build(self) -> Config {
+        let data_dir = self
+            .data_dir
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+
+        Config {
+            local_players_dir: self
+                .local_players_dir
+                .unwrap_or_else(|| data_dir.join(LOCAL_PLAYERS_DIRNAME)),
+            public_players_dir: self
+                .public_players_dir
+                .unwrap_or_else(|| data_dir.join(PUBLIC_PLAYERS_DIRNAME)),
+            proofs_dir: self
+                .proofs_dir
+                .unwrap_or_else(|| data_dir.join(PROOFS_DIRNAME)),
+            games_file: self
+                .games_file
+                .unwrap_or_else(|| data_dir.join(GAMES_FILENAME)),
+            accepted_vks: self.accepted_vks,
+            ledger_file: self.ledger_file,
+        }
+    }
+
+    /// Resolve every relative path set against `base_dir`
+    fn relative_to(self, base_dir: &Path) -> Self {
+        let resolve = |path: Option<PathBuf>| path.map(|p| base_dir.join(p));
+
+        ConfigBuilder {
+            data_dir: resolve(self.data_dir).or_else(|| Some(base_dir.join(DEFAULT_DATA_DIR))),
+            local_players_dir: resolve(self.local_players_dir),
+            public_players_dir: resolve(self.public_players_dir),
+            proofs_dir: resolve(self.proofs_dir),
+            games_file: resolve(self.games_file),
+            accepted_vks: self.accepted_vks,
+            ledger_file: resolve(self.ledger_file),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::Config;
+
+    #[test]
+    fn test_builder_paths() {
+        let config = Config::builder()
+            .data_dir("/tmp/zk-games")
+            .games_file("/tmp/other/games.json")
+            .accepted_vk("0x1234")
+            .build();
+
+        assert_eq!(
+            config.local_players_dir,
+            PathBuf::from("/tmp/zk-games/local-players")
+        );
+        assert_eq!(
+            config.public_players_dir,
+            PathBuf::from("/tmp/zk-games/public-players")
+        );
+        assert_eq!(config.proofs_dir, PathBuf::from("/tmp/zk-games/proofs"));
+        assert_eq!(config.games_file, PathBuf::from("/tmp/other/games.json"));
+        assert_eq!(config.accepted_vks, vec!["0x1234".to_string()]);
+        assert_eq!(config.ledger_file, None);
+    }
+}
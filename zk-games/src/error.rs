@@ -0,0 +1,98 @@
+use thiserror::Error;
+
+use crate::{games::rps_basic::TransitionError, prover::ProofMode};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Account {0} doesn't exists, please try to recover")]
+    AccountNotFound(String),
+
+    #[error("Account {0} already exists")]
+    AccountExists(String),
+
+    #[error("Secret of {0} is corrupted")]
+    CorruptSecret(String),
+
+    #[error("Public account of {0} is corrupted")]
+    CorruptAccount(String),
+
+    #[error("Wrong password for {0}")]
+    InvalidPassword(String),
+
+    #[error("Key derivation failed: {0}")]
+    Kdf(String),
+
+    #[error("Game with ID {0} not found")]
+    GameNotFound(u64),
+
+    #[error("Game with ID {0} already exists")]
+    GameExists(u64),
+
+    #[error("Game with ID {0} was changed by another client, please try again")]
+    GameConflict(u64),
+
+    #[error("Games file is corrupted: {0}")]
+    CorruptGamesFile(String),
+
+    #[error("Login challenges file is corrupted: {0}")]
+    CorruptChallenges(String),
+
+    #[error("Ledger file is corrupted: {0}")]
+    CorruptLedger(String),
+
+    #[error("Game storage failed: {0}")]
+    Storage(String),
+
+    #[error("Choice nonce of game {0} not found")]
+    NonceNotFound(u64),
+
+    #[error("No choice of game {0} matches its choice hash")]
+    ChoiceNotFound(u64),
+
+    #[error("Invalid config: {0}")]
+    Config(String),
+
+    #[error("Invalid prover config: {0}")]
+    ProverConfig(String),
+
+    #[error("Program execution failed: {0}")]
+    Execution(String),
+
+    #[error("Proof generation failed: {0}")]
+    Proving(String),
+
+    #[error("Proof verification failed: {0}")]
+    Verification(String),
+
+    #[error("{0:?} proofs are not accepted")]
+    UnsupportedProofMode(ProofMode),
+
+    #[error("Invalid public values: {0}")]
+    InvalidPublicValues(String),
+
+    #[error("Stored proof is corrupted: {0}")]
+    CorruptProof(String),
+
+    #[error("{0} still has to reveal games {1:?}, the secret can't change before")]
+    PendingCommitments(String, Vec<u64>),
+
+    #[error("Login challenge was not issued by this verifier")]
+    UnknownChallenge,
+
+    #[error("Login challenge has expired")]
+    ChallengeExpired,
+
+    #[error("Login challenge was already used")]
+    ChallengeReplayed,
+
+    #[error("Login proof is meant for client {0}")]
+    WrongAudience(String),
+
+    #[error(transparent)]
+    GameTransition(#[from] TransitionError),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,947 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zk_games_types::{commitment::CommitmentVersion, GameResult};
+
+use super::{calculate_result, Choice};
+
+/// Seconds both players have to reveal once player 2 joined, when the game doesn't set it
+pub const DEFAULT_REVEAL_TIMEOUT: u64 = 10 * 60;
+
+/// Seconds a new game waits for a player 2 before it expires, when the client doesn't set it
+pub const DEFAULT_OPEN_TIMEOUT: u64 = 24 * 60 * 60;
+
+fn default_reveal_timeout() -> u64 {
+    DEFAULT_REVEAL_TIMEOUT
+}
+
+/// Player 1 starts the game with the hash of his choice
+/// To finalize the game, player1 must send proof of his choice after player2 chose his choice
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Player1Info {
+    pub username: String,
+    pub choice_hash: [u8; 32],
+    /// Games saved before commitment versions are V1
+    #[serde(default)]
+    pub commitment_version: CommitmentVersion,
+}
+
+/// How player 2 plays, picked by player 1 when creating the game
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// Player 2 sends his choice as simple string when joining
+    #[default]
+    Plain,
+    /// Player 2 commits to his choice when joining and reveals it with a proof, like player 1
+    Hidden,
+}
+
+/// Choice of player 2, public or hidden behind a commitment until revealed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Player2Choice {
+    Plain(Choice),
+    Hidden {
+        choice_hash: [u8; 32],
+        commitment_version: CommitmentVersion,
+        revealed: Option<Choice>,
+    },
+}
+
+impl Player2Choice {
+    pub fn mode(&self) -> GameMode {
+        match self {
+            Player2Choice::Plain(_) => GameMode::Plain,
+            Player2Choice::Hidden { .. } => GameMode::Hidden,
+        }
+    }
+
+    /// The choice, once it is known
+    pub fn choice(&self) -> Option<Choice> {
+        match self {
+            Player2Choice::Plain(choice) => Some(*choice),
+            Player2Choice::Hidden { revealed, .. } => *revealed,
+        }
+    }
+}
+
+/// Player2 joins a game with his choice, see [`GameMode`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Player2Info {
+    pub username: String,
+    pub choice: Player2Choice,
+}
+
+/// vk digests of the programs allowed to complete a game, pinned when it is created
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PinnedVks {
+    /// rps-basic, reveals a choice hash
+    pub reveal: String,
+    /// rps-result, settles the game from the choice hash of player 1
+    pub result: String,
+}
+
+/// A program whose proofs complete a game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionProgram {
+    Reveal,
+    Result,
+}
+
+/// Where a game is in its lifecycle, see [`GameState`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameStatus {
+    Open,
+    Joined,
+    Completed,
+    Forfeited,
+    Cancelled,
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "status")]
+pub enum GameState {
+    /// Waiting for a player 2
+    Open,
+    /// Waiting for the hidden choices to be revealed before the deadline
+    Joined {
+        player2: Player2Info,
+        /// Games joined before deadlines have none until a store starts it,
+        /// see [`Game::start_reveal_deadline`]
+        reveal_deadline: Option<u64>,
+        /// Choice of player 1 when he revealed before a hidden player 2
+        #[serde(default)]
+        player1_choice: Option<Choice>,
+    },
+    /// Both choices were revealed
+    Completed {
+        player2: Player2Info,
+        result: GameResult,
+    },
+    /// A player didn't reveal in time and the other claimed the win,
+    /// a draw when neither revealed
+    Forfeited {
+        player2: Player2Info,
+        result: GameResult,
+    },
+    /// Player 1 closed the game before anyone joined
+    Cancelled,
+    /// Nobody joined before the game stopped being open
+    Expired,
+}
+
+/// A rejected game transition
+#[derive(Error, Debug, PartialEq)]
+pub enum TransitionError {
+    #[error("Game {game_id} is {status:?}, it can't be joined")]
+    NotOpen { game_id: u64, status: GameStatus },
+
+    #[error("Game {game_id} is {status:?}, it has no pending reveal")]
+    NotJoined { game_id: u64, status: GameStatus },
+
+    #[error("Player 1 can't join his own game {0}")]
+    SelfJoin(u64),
+
+    #[error("Game {game_id} is played in {expected:?} mode")]
+    WrongMode { game_id: u64, expected: GameMode },
+
+    #[error("{username} is not player 1 of game {game_id}")]
+    NotPlayer1 { game_id: u64, username: String },
+
+    #[error("{username} is not a player of game {game_id}")]
+    NotAPlayer { game_id: u64, username: String },
+
+    #[error("Choice of {username} in game {game_id} is already known")]
+    AlreadyRevealed { game_id: u64, username: String },
+
+    #[error("{username} didn't reveal in game {game_id}, only his opponent can claim it")]
+    NotRevealed { game_id: u64, username: String },
+
+    #[error("Reveal deadline of game {0} has passed")]
+    DeadlinePassed(u64),
+
+    #[error("Reveal deadline of game {0} has not passed yet")]
+    DeadlineNotPassed(u64),
+
+    #[error("Game {0} is no longer open to join")]
+    OpenPeriodOver(u64),
+
+    #[error("Game {0} is still open to join")]
+    OpenPeriodNotOver(u64),
+
+    #[error("Game {game_id} doesn't accept proofs of vk {vk_hash}")]
+    UnpinnedVk { game_id: u64, vk_hash: String },
+}
+
+/// A rock paper scissors game, every change goes through a validated transition
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "GameRecord")]
+pub struct Game {
+    pub id: u64,
+    pub player1: Player1Info,
+    /// How player 2 has to join
+    pub mode: GameMode,
+    /// Seconds the players have to reveal once player 2 joined
+    pub reveal_timeout: u64,
+    /// Until when player 2 can join, games saved before expiry never expire
+    pub open_until: Option<u64>,
+    /// Games saved before pinning accept any vk
+    pub vks: Option<PinnedVks>,
+    #[serde(flatten)]
+    state: GameState,
+}
+
+impl Game {
+    pub fn new(id: u64, player1: Player1Info, reveal_timeout: u64) -> Self {
+        Game {
+            id,
+            player1,
+            mode: GameMode::Plain,
+            reveal_timeout,
+            open_until: None,
+            vks: None,
+            state: GameState::Open,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: GameMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Expire the game if nobody joined it by `open_until`
+    pub fn with_open_until(mut self, open_until: u64) -> Self {
+        self.open_until = Some(open_until);
+        self
+    }
+
+    /// Only accept completion proofs of these programs, or of the allow-listed vks
+    pub fn with_vks(mut self, vks: PinnedVks) -> Self {
+        self.vks = Some(vks);
+        self
+    }
+
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match self.state {
+            GameState::Open => GameStatus::Open,
+            GameState::Joined { .. } => GameStatus::Joined,
+            GameState::Completed { .. } => GameStatus::Completed,
+            GameState::Forfeited { .. } => GameStatus::Forfeited,
+            GameState::Cancelled => GameStatus::Cancelled,
+            GameState::Expired => GameStatus::Expired,
+        }
+    }
+
+    /// Open and still inside its open period
+    pub fn is_joinable(&self, now: u64) -> bool {
+        self.state == GameState::Open && !self.open_period_over(now)
+    }
+
+    fn open_period_over(&self, now: u64) -> bool {
+        self.open_until.is_some_and(|open_until| now > open_until)
+    }
+
+    pub fn player2(&self) -> Option<&Player2Info> {
+        match &self.state {
+            GameState::Joined { player2, .. }
+            | GameState::Completed { player2, .. }
+            | GameState::Forfeited { player2, .. } => Some(player2),
+            GameState::Open | GameState::Cancelled | GameState::Expired => None,
+        }
+    }
+
+    /// A proof of `vk_hash` can complete the game if it is the vk pinned for the program,
+    /// or one of `accepted_vks` allowed for a planned upgrade
+    pub fn check_vk(
+        &self,
+        program: CompletionProgram,
+        vk_hash: &str,
+        accepted_vks: &[String],
+    ) -> Result<(), TransitionError> {
+        let Some(vks) = &self.vks else {
+            return Ok(());
+        };
+        let pinned = match program {
+            CompletionProgram::Reveal => &vks.reveal,
+            CompletionProgram::Result => &vks.result,
+        };
+
+        if pinned != vk_hash && !accepted_vks.iter().any(|vk| vk == vk_hash) {
+            return Err(TransitionError::UnpinnedVk {
+                game_id: self.id,
+                vk_hash: vk_hash.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Commitment `username` has to reveal in this game
+    pub fn commitment_of(&self, username: &str) -> Option<([u8; 32], CommitmentVersion)> {
+        if username == self.player1.username {
+            return Some((self.player1.choice_hash, self.player1.commitment_version));
+        }
+
+        match self.player2() {
+            Some(Player2Info {
+                username: player2,
+                choice:
+                    Player2Choice::Hidden {
+                        choice_hash,
+                        commitment_version,
+                        ..
+                    },
+            }) if player2 == username => Some((*choice_hash, *commitment_version)),
+            _ => None,
+        }
+    }
+
+    /// Final result of a settled game
+    pub fn result(&self) -> Option<GameResult> {
+        match &self.state {
+            GameState::Completed { result, .. } | GameState::Forfeited { result, .. } => {
+                Some(result.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Until when the players can reveal, once the game is joined
+    pub fn reveal_deadline(&self) -> Option<u64> {
+        match self.state {
+            GameState::Joined {
+                reveal_deadline, ..
+            } => reveal_deadline,
+            _ => None,
+        }
+    }
+
+    /// Joined before deadlines existed, its deadline was never started
+    pub fn lacks_reveal_deadline(&self) -> bool {
+        matches!(
+            self.state,
+            GameState::Joined {
+                reveal_deadline: None,
+                ..
+            }
+        )
+    }
+
+    /// Give a game joined without a deadline a full reveal timeout from `now`.
+    /// Stores do it the first time they load the game and save it, so it only happens once.
+    pub fn start_reveal_deadline(&mut self, now: u64) {
+        if let GameState::Joined {
+            reveal_deadline: reveal_deadline @ None,
+            ..
+        } = &mut self.state
+        {
+            *reveal_deadline = Some(now + self.reveal_timeout);
+        }
+    }
+
+    /// Whether `username` committed to a choice they may still have to reveal.
+    /// The commitment is bound to their current secret, it must not change meanwhile.
+    pub fn awaits_reveal_from(&self, username: &str, now: u64) -> bool {
+        match self.state {
+            GameState::Open => username == self.player1.username && self.is_joinable(now),
+            GameState::Joined { .. } => self.check_reveal(username, now).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Check that `username` can join in `mode`, without changing the game
+    pub fn check_join(
+        &self,
+        username: &str,
+        mode: GameMode,
+        now: u64,
+    ) -> Result<(), TransitionError> {
+        if self.state != GameState::Open {
+            return Err(self.not_open());
+        }
+        if self.open_period_over(now) {
+            return Err(TransitionError::OpenPeriodOver(self.id));
+        }
+        if username == self.player1.username {
+            return Err(TransitionError::SelfJoin(self.id));
+        }
+        if mode != self.mode {
+            return Err(TransitionError::WrongMode {
+                game_id: self.id,
+                expected: self.mode,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Open -> Joined, the hidden choices must be revealed within the game reveal timeout
+    pub fn join(&mut self, player2: Player2Info, now: u64) -> Result<(), TransitionError> {
+        self.check_join(&player2.username, player2.choice.mode(), now)?;
+
+        self.state = GameState::Joined {
+            player2,
+            reveal_deadline: Some(now + self.reveal_timeout),
+            player1_choice: None,
+        };
+        Ok(())
+    }
+
+    /// Check that `username` can still reveal, without changing the game
+    pub fn check_reveal(&self, username: &str, now: u64) -> Result<(), TransitionError> {
+        let GameState::Joined {
+            player2,
+            reveal_deadline,
+            player1_choice,
+        } = &self.state
+        else {
+            return Err(self.not_joined());
+        };
+
+        let already_revealed = if username == self.player1.username {
+            player1_choice.is_some()
+        } else if username == player2.username {
+            player2.choice.choice().is_some()
+        } else {
+            return Err(TransitionError::NotAPlayer {
+                game_id: self.id,
+                username: username.to_string(),
+            });
+        };
+        if already_revealed {
+            return Err(TransitionError::AlreadyRevealed {
+                game_id: self.id,
+                username: username.to_string(),
+            });
+        }
+        if reveal_deadline.is_some_and(|deadline| now > deadline) {
+            return Err(TransitionError::DeadlinePassed(self.id));
+        }
+
+        Ok(())
+    }
+
+    /// Record the verified choice of `username`, Joined -> Completed once both choices are known
+    pub fn reveal(
+        &mut self,
+        username: &str,
+        choice: Choice,
+        now: u64,
+    ) -> Result<Option<GameResult>, TransitionError> {
+        self.check_reveal(username, now)?;
+        let GameState::Joined {
+            player2,
+            player1_choice,
+            ..
+        } = &mut self.state
+        else {
+            return Err(self.not_joined());
+        };
+
+        if username == self.player1.username {
+            *player1_choice = Some(choice);
+        } else if let Player2Choice::Hidden { revealed, .. } = &mut player2.choice {
+            *revealed = Some(choice);
+        }
+
+        let (Some(choice_1), Some(choice_2)) = (*player1_choice, player2.choice.choice()) else {
+            return Ok(None);
+        };
+        let result = calculate_result(choice_1 as u8, choice_2 as u8);
+        self.state = GameState::Completed {
+            player2: player2.clone(),
+            result: result.clone(),
+        };
+        Ok(Some(result))
+    }
+
+    /// Joined -> Completed with a result proven by player 1 against the known choice of player 2
+    pub fn settle(
+        &mut self,
+        username: &str,
+        result: GameResult,
+        now: u64,
+    ) -> Result<GameResult, TransitionError> {
+        if username != self.player1.username {
+            return Err(TransitionError::NotPlayer1 {
+                game_id: self.id,
+                username: username.to_string(),
+            });
+        }
+        self.check_reveal(username, now)?;
+        let Some(player2) = self.player2().cloned() else {
+            return Err(self.not_joined());
+        };
+        if player2.choice.choice().is_none() {
+            return Err(TransitionError::NotRevealed {
+                game_id: self.id,
+                username: player2.username,
+            });
+        }
+
+        self.state = GameState::Completed {
+            player2,
+            result: result.clone(),
+        };
+        Ok(result)
+    }
+
+    /// Result `username` gets by claiming the timeout, without changing the game
+    pub fn check_claim_timeout(
+        &self,
+        username: &str,
+        now: u64,
+    ) -> Result<GameResult, TransitionError> {
+        let GameState::Joined {
+            player2,
+            reveal_deadline,
+            player1_choice,
+        } = &self.state
+        else {
+            return Err(self.not_joined());
+        };
+        if username != self.player1.username && username != player2.username {
+            return Err(TransitionError::NotAPlayer {
+                game_id: self.id,
+                username: username.to_string(),
+            });
+        }
+        // A deadline that never started can't pass
+        if reveal_deadline.is_none_or(|deadline| now <= deadline) {
+            return Err(TransitionError::DeadlineNotPassed(self.id));
+        }
+
+        // Whoever didn't reveal loses, only the other player can claim
+        let result = match (player1_choice, player2.choice.choice()) {
+            (Some(_), None) if username == self.player1.username => GameResult::Player1ByForfeit,
+            (None, Some(_)) if username == player2.username => GameResult::Player2ByForfeit,
+            (None, None) => GameResult::Draw,
+            _ => {
+                return Err(TransitionError::NotRevealed {
+                    game_id: self.id,
+                    username: username.to_string(),
+                })
+            }
+        };
+
+        Ok(result)
+    }
+
+    /// Joined -> Forfeited once the reveal deadline passed
+    pub fn claim_timeout(
+        &mut self,
+        username: &str,
+        now: u64,
+    ) -> Result<GameResult, TransitionError> {
+        let result = self.check_claim_timeout(username, now)?;
+        let Some(player2) = self.player2().cloned() else {
+            return Err(self.not_joined());
+        };
+
+        self.state = GameState::Forfeited {
+            player2,
+            result: result.clone(),
+        };
+        Ok(result)
+    }
+
+    /// Open -> Cancelled, only player 1 can cancel
+    pub fn cancel(&mut self, username: &str) -> Result<(), TransitionError> {
+        if self.state != GameState::Open {
+            return Err(self.not_open());
+        }
+        if username != self.player1.username {
+            return Err(TransitionError::NotPlayer1 {
+                game_id: self.id,
+                username: username.to_string(),
+            });
+        }
+
+        self.state = GameState::Cancelled;
+        Ok(())
+    }
+
+    /// Open -> Expired once the open period is over, anyone can expire a game
+    pub fn expire(&mut self, now: u64) -> Result<(), TransitionError> {
+        if self.state != GameState::Open {
+            return Err(self.not_open());
+        }
+        if !self.open_period_over(now) {
+            return Err(TransitionError::OpenPeriodNotOver(self.id));
+        }
+
+        self.state = GameState::Expired;
+        Ok(())
+    }
+
+    fn not_open(&self) -> TransitionError {
+        TransitionError::NotOpen {
+            game_id: self.id,
+            status: self.status(),
+        }
+    }
+
+    fn not_joined(&self) -> TransitionError {
+        TransitionError::NotJoined {
+            game_id: self.id,
+            status: self.status(),
+        }
+    }
+}
+
+/// Stored game, games saved before the state machine have no status
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GameRecord {
+    Current {
+        id: u64,
+        player1: Player1Info,
+        #[serde(default)]
+        mode: GameMode,
+        #[serde(default = "default_reveal_timeout")]
+        reveal_timeout: u64,
+        #[serde(default)]
+        open_until: Option<u64>,
+        #[serde(default)]
+        vks: Option<PinnedVks>,
+        #[serde(flatten)]
+        state: GameState,
+    },
+    Legacy {
+        id: u64,
+        player1: Player1Info,
+        player2: Option<Player2Info>,
+        timeout: Option<u64>,
+        result: Option<GameResult>,
+    },
+}
+
+impl From<GameRecord> for Game {
+    fn from(record: GameRecord) -> Self {
+        match record {
+            GameRecord::Current {
+                id,
+                player1,
+                mode,
+                reveal_timeout,
+                open_until,
+                vks,
+                state,
+            } => Game {
+                id,
+                player1,
+                mode,
+                reveal_timeout,
+                open_until,
+                vks,
+                state,
+            },
+            GameRecord::Legacy {
+                id,
+                player1,
+                player2,
+                timeout,
+                result,
+            } => {
+                let state = match (player2, result) {
+                    (Some(player2), Some(result)) => GameState::Completed { player2, result },
+                    // Without a deadline, the store starts one the first time it loads the game
+                    (Some(player2), None) => GameState::Joined {
+                        player2,
+                        reveal_deadline: timeout,
+                        player1_choice: None,
+                    },
+                    (None, _) => GameState::Open,
+                };
+                Game {
+                    id,
+                    player1,
+                    mode: GameMode::Plain,
+                    reveal_timeout: DEFAULT_REVEAL_TIMEOUT,
+                    open_until: None,
+                    vks: None,
+                    state,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use zk_games_types::GameResult;
+
+    use super::{
+        CompletionProgram, Game, GameMode, GameStatus, PinnedVks, Player1Info, Player2Choice,
+        Player2Info, TransitionError, DEFAULT_REVEAL_TIMEOUT,
+    };
+    use crate::games::rps_basic::Choice;
+
+    fn new_game() -> Game {
+        Game::new(
+            7,
+            Player1Info {
+                username: "player1".to_string(),
+                choice_hash: [1; 32],
+                commitment_version: Default::default(),
+            },
+            100,
+        )
+    }
+
+    fn player2() -> Player2Info {
+        Player2Info {
+            username: "player2".to_string(),
+            choice: Player2Choice::Plain(Choice::Scissors),
+        }
+    }
+
+    fn hidden_player2() -> Player2Info {
+        Player2Info {
+            username: "player2".to_string(),
+            choice: Player2Choice::Hidden {
+                choice_hash: [2; 32],
+                commitment_version: Default::default(),
+                revealed: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_transitions() {
+        let mut game = new_game();
+        assert_eq!(
+            game.join(
+                Player2Info {
+                    username: "player1".to_string(),
+                    choice: Player2Choice::Plain(Choice::Rock),
+                },
+                0
+            ),
+            Err(TransitionError::SelfJoin(7))
+        );
+        assert!(matches!(
+            game.join(hidden_player2(), 0),
+            Err(TransitionError::WrongMode { .. })
+        ));
+
+        game.join(player2(), 0).unwrap();
+        assert_eq!(game.status(), GameStatus::Joined);
+        assert_eq!(game.reveal_deadline(), Some(100));
+
+        // Joining twice is an error, not a silent no-op
+        assert_eq!(
+            game.join(player2(), 0),
+            Err(TransitionError::NotOpen {
+                game_id: 7,
+                status: GameStatus::Joined
+            })
+        );
+        assert_eq!(
+            game.claim_timeout("player2", 100),
+            Err(TransitionError::DeadlineNotPassed(7))
+        );
+        assert!(matches!(
+            game.reveal("player2", Choice::Rock, 50),
+            Err(TransitionError::AlreadyRevealed { .. })
+        ));
+
+        let result = game.reveal("player1", Choice::Rock, 50).unwrap();
+        assert_eq!(result, Some(GameResult::Player1));
+        assert_eq!(game.status(), GameStatus::Completed);
+        assert!(game.cancel("player1").is_err());
+
+        // A proven result settles the game as is
+        let mut game = new_game();
+        game.join(player2(), 0).unwrap();
+        assert!(matches!(
+            game.settle("player2", GameResult::Player2, 50),
+            Err(TransitionError::NotPlayer1 { .. })
+        ));
+        assert_eq!(
+            game.settle("player1", GameResult::Player1, 50),
+            Ok(GameResult::Player1)
+        );
+        assert_eq!(game.result(), Some(GameResult::Player1));
+    }
+
+    #[test]
+    fn test_hidden_mode() {
+        let mut game = new_game().with_mode(GameMode::Hidden);
+        assert!(matches!(
+            game.join(player2(), 0),
+            Err(TransitionError::WrongMode { .. })
+        ));
+        game.join(hidden_player2(), 0).unwrap();
+        assert_eq!(game.commitment_of("player2").unwrap().0, [2; 32]);
+        assert!(matches!(
+            game.settle("player1", GameResult::Player1, 10),
+            Err(TransitionError::NotRevealed { .. })
+        ));
+
+        // Nothing is settled until both choices are revealed
+        assert_eq!(game.reveal("player2", Choice::Paper, 10), Ok(None));
+        assert_eq!(game.status(), GameStatus::Joined);
+        assert_eq!(
+            game.reveal("player1", Choice::Rock, 20),
+            Ok(Some(GameResult::Player2))
+        );
+        assert_eq!(game.status(), GameStatus::Completed);
+    }
+
+    #[test]
+    fn test_claim_timeout_and_cancel() {
+        let mut game = new_game();
+        game.join(player2(), 0).unwrap();
+        assert_eq!(
+            game.reveal("player1", Choice::Rock, 101),
+            Err(TransitionError::DeadlinePassed(7))
+        );
+        assert!(matches!(
+            game.claim_timeout("player1", 101),
+            Err(TransitionError::NotRevealed { .. })
+        ));
+        game.claim_timeout("player2", 101).unwrap();
+        assert_eq!(game.status(), GameStatus::Forfeited);
+        assert_eq!(game.result(), Some(GameResult::Player2ByForfeit));
+
+        // A hidden player 2 who doesn't reveal loses too
+        let mut game = new_game().with_mode(GameMode::Hidden);
+        game.join(hidden_player2(), 0).unwrap();
+        game.reveal("player1", Choice::Rock, 50).unwrap();
+        assert!(matches!(
+            game.claim_timeout("player2", 101),
+            Err(TransitionError::NotRevealed { .. })
+        ));
+        assert_eq!(
+            game.claim_timeout("player1", 101),
+            Ok(GameResult::Player1ByForfeit)
+        );
+
+        // Nobody revealed, nobody wins
+        let mut game = new_game().with_mode(GameMode::Hidden);
+        game.join(hidden_player2(), 0).unwrap();
+        assert_eq!(game.claim_timeout("player2", 101), Ok(GameResult::Draw));
+
+        let mut game = new_game();
+        assert!(matches!(
+            game.cancel("player2"),
+            Err(TransitionError::NotPlayer1 { .. })
+        ));
+        game.cancel("player1").unwrap();
+        assert_eq!(game.status(), GameStatus::Cancelled);
+        assert!(game.join(player2(), 0).is_err());
+    }
+
+    #[test]
+    fn test_expire() {
+        let mut game = new_game().with_open_until(50);
+        assert!(game.is_joinable(50));
+        assert_eq!(game.expire(50), Err(TransitionError::OpenPeriodNotOver(7)));
+
+        assert!(!game.is_joinable(51));
+        assert_eq!(
+            game.join(player2(), 51),
+            Err(TransitionError::OpenPeriodOver(7))
+        );
+        game.expire(51).unwrap();
+        assert_eq!(game.status(), GameStatus::Expired);
+        assert!(game.cancel("player1").is_err());
+
+        // Games without an open period never expire
+        let mut game = new_game();
+        assert!(game.is_joinable(u64::MAX));
+        assert!(game.expire(u64::MAX).is_err());
+    }
+
+    #[test]
+    fn test_awaits_reveal() {
+        let mut game = new_game().with_mode(GameMode::Hidden).with_open_until(50);
+        assert!(game.awaits_reveal_from("player1", 0));
+        assert!(!game.awaits_reveal_from("player2", 0));
+        // Nobody can join anymore, the commitment will never be revealed
+        assert!(!game.awaits_reveal_from("player1", 51));
+
+        game.join(hidden_player2(), 0).unwrap();
+        assert!(game.awaits_reveal_from("player2", 0));
+        game.reveal("player2", Choice::Paper, 10).unwrap();
+        assert!(!game.awaits_reveal_from("player2", 10));
+        assert!(game.awaits_reveal_from("player1", 10));
+        assert!(!game.awaits_reveal_from("player1", 101));
+    }
+
+    #[test]
+    fn test_pinned_vks() {
+        let game = new_game().with_vks(PinnedVks {
+            reveal: "0xreveal".to_string(),
+            result: "0xresult".to_string(),
+        });
+        assert!(game
+            .check_vk(CompletionProgram::Reveal, "0xreveal", &[])
+            .is_ok());
+        assert!(game
+            .check_vk(CompletionProgram::Result, "0xresult", &[])
+            .is_ok());
+
+        // Another build of the program is refused, unless it is allow-listed
+        assert_eq!(
+            game.check_vk(CompletionProgram::Reveal, "0xresult", &[]),
+            Err(TransitionError::UnpinnedVk {
+                game_id: 7,
+                vk_hash: "0xresult".to_string()
+            })
+        );
+        assert!(game
+            .check_vk(
+                CompletionProgram::Reveal,
+                "0xupgrade",
+                &["0xupgrade".to_string()]
+            )
+            .is_ok());
+
+        // Games saved before pinning accept any vk
+        assert!(new_game()
+            .check_vk(CompletionProgram::Reveal, "0xanything", &[])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_legacy_game() {
+        let json = r#"{"id":0,"player1":{"username":"player1","choice_hash":[208,134,76,1,23,95,153,33,87,165,74,23,52,254,58,94,244,17,183,128,252,186,6,126,4,238,210,245,133,18,54,203]},"player2":{"username":"player2","choice":"Rock"},"timeout":1747202247,"result":"Player1"}"#;
+        let game: Game = serde_json::from_str(json).unwrap();
+        assert_eq!(game.status(), GameStatus::Completed);
+        assert_eq!(game.result(), Some(GameResult::Player1));
+
+        // Saved games keep their status
+        let saved = serde_json::to_string(&game).unwrap();
+        assert!(saved.contains(r#""status":"Completed""#));
+        assert_eq!(serde_json::from_str::<Game>(&saved).unwrap(), game);
+
+        // A joined game without a deadline can't be claimed until one is started
+        let json = r#"{"id":1,"player1":{"username":"player1","choice_hash":[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]},"player2":{"username":"player2","choice":"Rock"}}"#;
+        let mut game: Game = serde_json::from_str(json).unwrap();
+        assert_eq!(game.status(), GameStatus::Joined);
+        assert!(game.lacks_reveal_deadline());
+        assert_eq!(game.reveal_deadline(), None);
+        assert_eq!(
+            game.check_claim_timeout("player2", u64::MAX),
+            Err(TransitionError::DeadlineNotPassed(1))
+        );
+        assert!(game.check_reveal("player1", u64::MAX).is_ok());
+
+        // Once started the deadline is saved with the game
+        game.start_reveal_deadline(1_000);
+        assert_eq!(game.reveal_deadline(), Some(1_000 + DEFAULT_REVEAL_TIMEOUT));
+        game.start_reveal_deadline(2_000);
+        assert_eq!(game.reveal_deadline(), Some(1_000 + DEFAULT_REVEAL_TIMEOUT));
+        let saved = serde_json::to_string(&game).unwrap();
+        assert_eq!(serde_json::from_str::<Game>(&saved).unwrap(), game);
+        assert_eq!(
+            game.check_claim_timeout("player2", 1_001 + DEFAULT_REVEAL_TIMEOUT),
+            Ok(GameResult::Player2ByForfeit)
+        );
+    }
+}
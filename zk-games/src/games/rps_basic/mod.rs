@@ -1,14 +1,32 @@
-use std::{env, fs, path::Path};
+use std::{fs, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
-use sp1_sdk::{HashableKey, Prover, ProverClient, SP1Stdin};
-use zk_games_types::{GameResult, RpsBasicInput};
+use sp1_sdk::{HashableKey, SP1Proof, SP1ProofWithPublicValues, SP1Stdin};
+use zk_games_types::{
+    commitment::{choice_commitment, CommitmentVersion},
+    RpsAggregateInput, RpsAggregatePublic, RpsBasicInput, RpsBasicPublic, RpsResultInput,
+    RpsResultPublic,
+};
 
-use crate::zk::get_secret;
+use crate::{
+    programs,
+    prover::{ProofMode, ProverBackend},
+    user::Account,
+    Config, Error, ProofVerifier, Result,
+};
+
+pub use zk_games_types::calculate_result;
+
+pub mod game;
+pub mod store;
+
+pub use game::{
+    CompletionProgram, Game, GameMode, GameState, GameStatus, PinnedVks, Player1Info,
+    Player2Choice, Player2Info, TransitionError, DEFAULT_OPEN_TIMEOUT, DEFAULT_REVEAL_TIMEOUT,
+};
 
 #[repr(u8)]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Choice {
     Rock = 0,
     Paper = 1,
@@ -18,7 +36,7 @@ pub enum Choice {
 impl TryFrom<u8> for Choice {
     type Error = ();
 
-    fn try_from(v: u8) -> Result<Self, Self::Error> {
+    fn try_from(v: u8) -> std::result::Result<Self, Self::Error> {
         match v {
             x if x == Choice::Rock as u8 => Ok(Choice::Rock),
             x if x == Choice::Paper as u8 => Ok(Choice::Paper),
@@ -28,124 +46,459 @@ impl TryFrom<u8> for Choice {
     }
 }
 
-pub fn generate_basic_choice_hash(
+impl PinnedVks {
+    /// vk digests of the programs built into this binary
+    pub fn current() -> Self {
+        PinnedVks {
+            reveal: programs::RPS_BASIC.vk_hash().to_string(),
+            result: programs::RPS_RESULT.vk_hash().to_string(),
+        }
+    }
+}
+
+pub const NONCES_DIRNAME: &str = "nonces";
+
+/// Commitment version used for new games
+pub const CHOICE_COMMITMENT_VERSION: CommitmentVersion = CommitmentVersion::V2;
+
+/// A choice hash committed in a game, what the reveal and result programs open
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChoiceCommitment {
+    pub client_pubkey: String,
+    pub game_id: u64,
+    pub choice_hash: [u8; 32],
+    pub version: CommitmentVersion,
+}
+
+impl ChoiceCommitment {
+    /// Commitment `username` has to reveal in the game
+    pub fn of(game: &Game, username: &str, client_pubkey: &str) -> Option<Self> {
+        let (choice_hash, version) = game.commitment_of(username)?;
+
+        Some(ChoiceCommitment {
+            client_pubkey: client_pubkey.to_string(),
+            game_id: game.id,
+            choice_hash,
+            version,
+        })
+    }
+}
+
+fn opening_path(config: &Config, username: &str, client_pubkey: &str, game_id: u64) -> PathBuf {
+    config
+        .local_players_dir
+        .join(username)
+        .join(NONCES_DIRNAME)
+        .join(format!("{client_pubkey}-{game_id}"))
+}
+
+/// The nonce and the choice are needed to reveal later, so they stay with the local secret
+fn save_opening(
+    config: &Config,
     username: &str,
     client_pubkey: &str,
     game_id: u64,
+    nonce: [u8; 32],
     choice: u8,
-) -> Result<[u8; 32], String> {
-    let secret = get_secret(username)?;
+) -> Result<()> {
+    let path = opening_path(config, username, client_pubkey, game_id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
 
-    Ok(Sha256::new()
-        .chain_update(secret)
-        .chain_update(client_pubkey)
-        .chain_update(game_id.to_string())
-        .chain_update(choice.to_string())
-        .finalize()
-        .into())
+    fs::write(path, [nonce.as_slice(), &[choice]].concat())?;
+    Ok(())
 }
 
-pub fn generate_basic_game_proof<'a>(
+/// Nonce and choice behind a commitment, nonces saved before the choice come alone
+fn get_opening(
+    config: &Config,
     username: &str,
     client_pubkey: &str,
     game_id: u64,
-    choice_hash: [u8; 32],
-) -> Result<(Vec<u8>, Vec<u8>, String), String> {
-    let secret = get_secret(username)?;
+) -> Result<([u8; 32], Option<u8>)> {
+    let path = opening_path(config, username, client_pubkey, game_id);
+    if !path.exists() {
+        return Err(Error::NonceNotFound(game_id));
+    }
 
-    let rps_basic_input = RpsBasicInput {
-        client_pubkey: client_pubkey.to_string(),
+    let opening = fs::read(path)?;
+    match opening.split_first_chunk::<32>() {
+        Some((nonce, [])) => Ok((*nonce, None)),
+        Some((nonce, [choice])) => Ok((*nonce, Some(*choice))),
+        _ => Err(Error::NonceNotFound(game_id)),
+    }
+}
+
+/// Commit to a choice with a fresh blinding nonce, using the current commitment version
+pub fn generate_basic_choice_hash(
+    config: &Config,
+    account: &Account,
+    client_pubkey: &str,
+    game_id: u64,
+    choice: u8,
+) -> Result<[u8; 32]> {
+    let nonce: [u8; 32] = rand::random();
+
+    save_opening(
+        config,
+        &account.username,
+        client_pubkey,
         game_id,
-        choice_hash,
-        secret,
-    };
+        nonce,
+        choice,
+    )?;
 
-    // Start generating the proof
-    let client = ProverClient::builder()
-        .network()
-        .private_key(&env::var("SP1_NETWORK_PK").unwrap())
-        .rpc_url("https://rpc.production.succinct.xyz")
-        .build();
-    let rps_basic_elf = fs::read(Path::new(
-        "/mnt/extra/Projects/solana/zk-games/zk-games-programs/rps-basic/elf/rps-basic-zk-program",
+    Ok(choice_commitment(
+        CHOICE_COMMITMENT_VERSION,
+        account.secret(),
+        client_pubkey,
+        game_id,
+        choice,
+        &nonce,
     ))
-    .unwrap();
+}
+
+/// Nonce and choice opening our commitment, checked on the host so a wrong
+/// opening fails before proving. V1 commitments have no nonce.
+fn choice_opening(
+    config: &Config,
+    account: &Account,
+    commitment: &ChoiceCommitment,
+) -> Result<([u8; 32], u8)> {
+    let (nonce, choice) = match commitment.version {
+        CommitmentVersion::V1 => ([0; 32], None),
+        CommitmentVersion::V2 => get_opening(
+            config,
+            &account.username,
+            &commitment.client_pubkey,
+            commitment.game_id,
+        )?,
+    };
+    let opens = |choice: u8| {
+        choice_commitment(
+            commitment.version,
+            account.secret(),
+            &commitment.client_pubkey,
+            commitment.game_id,
+            choice,
+            &nonce,
+        ) == commitment.choice_hash
+    };
+
+    // Commitments made before the choice was stored can only be opened by trying each choice
+    let choice = match choice {
+        Some(choice) => Some(choice).filter(|&choice| opens(choice)),
+        None => (0..3).find(|&choice| opens(choice)),
+    };
+    choice
+        .map(|choice| (nonce, choice))
+        .ok_or(Error::ChoiceNotFound(commitment.game_id))
+}
+
+fn basic_game_stdin(
+    config: &Config,
+    account: &Account,
+    commitment: &ChoiceCommitment,
+) -> Result<SP1Stdin> {
+    let (nonce, choice) = choice_opening(config, account, commitment)?;
+
+    let rps_basic_input = RpsBasicInput {
+        client_pubkey: commitment.client_pubkey.clone(),
+        game_id: commitment.game_id,
+        choice_hash: commitment.choice_hash,
+        version: commitment.version,
+        nonce,
+        choice,
+        secret: *account.secret(),
+    };
 
-    let (pk, vk) = client.setup(rps_basic_elf.as_slice());
-    println!("vk: {:?}", vk.bytes32());
     let mut stdin = SP1Stdin::new();
     stdin.write(&rps_basic_input);
+    Ok(stdin)
+}
+
+/// Run the reveal program without proving, returns its cycle count
+pub fn basic_game_cycles(
+    config: &Config,
+    prover: &ProverBackend,
+    account: &Account,
+    commitment: &ChoiceCommitment,
+) -> Result<u64> {
+    let stdin = basic_game_stdin(config, account, commitment)?;
+    let (_, report) = prover.execute(&programs::RPS_BASIC, &stdin)?;
+
+    Ok(report.total_instruction_count())
+}
+
+/// Prove the choice behind our choice hash, the proof is encoded as [`ProofMode::encode`] does
+pub fn generate_basic_game_proof(
+    config: &Config,
+    prover: &ProverBackend,
+    account: &Account,
+    commitment: &ChoiceCommitment,
+    mode: ProofMode,
+) -> Result<(Vec<u8>, Vec<u8>, String)> {
+    let stdin = basic_game_stdin(config, account, commitment)?;
+
+    // Generate the proof
+    let proof = prover.prove(&programs::RPS_BASIC, &stdin, mode)?;
+
+    Ok((
+        mode.encode(&proof)?,
+        proof.public_values.to_vec(),
+        programs::RPS_BASIC.vk_hash().to_string(),
+    ))
+}
+
+/// Same reveal as [`generate_basic_game_proof`] but compressed, to be aggregated later
+pub fn generate_basic_game_compressed_proof(
+    config: &Config,
+    prover: &ProverBackend,
+    account: &Account,
+    commitment: &ChoiceCommitment,
+) -> Result<SP1ProofWithPublicValues> {
+    let stdin = basic_game_stdin(config, account, commitment)?;
+
+    prover.prove(&programs::RPS_BASIC, &stdin, ProofMode::Compressed)
+}
+
+/// Verify many compressed reveals inside the aggregation program,
+/// returns a single proof of all of them
+pub fn aggregate_basic_game_proofs(
+    prover: &ProverBackend,
+    proofs: Vec<SP1ProofWithPublicValues>,
+    mode: ProofMode,
+) -> Result<(Vec<u8>, Vec<u8>, String)> {
+    if proofs.is_empty() {
+        return Err(Error::Proving("no reveal proofs to aggregate".to_string()));
+    }
+
+    let rps_basic_vk = programs::RPS_BASIC.vk();
+    let rps_aggregate_input = RpsAggregateInput {
+        rps_basic_vkey: rps_basic_vk.hash_u32(),
+        public_values: proofs
+            .iter()
+            .map(|proof| proof.public_values.to_vec())
+            .collect(),
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&rps_aggregate_input);
+    for proof in proofs {
+        let SP1Proof::Compressed(proof) = proof.proof else {
+            return Err(Error::Proving(
+                "only compressed proofs can be aggregated".to_string(),
+            ));
+        };
+        stdin.write_proof(*proof, rps_basic_vk.vk.clone());
+    }
 
     // Generate the proof
-    let proof = client
-        .prove(&pk, &stdin)
-        .groth16()
-        .run()
-        .expect("Groth16 proof generation failed");
+    let proof = prover.prove(&programs::RPS_AGGREGATE, &stdin, mode)?;
+
+    Ok((
+        mode.encode(&proof)?,
+        proof.public_values.to_vec(),
+        programs::RPS_AGGREGATE.vk_hash().to_string(),
+    ))
+}
+
+/// Prove the result of a game against the public choice of player 2,
+/// the winner is computed inside the program
+pub fn generate_result_proof(
+    config: &Config,
+    prover: &ProverBackend,
+    account: &Account,
+    commitment: &ChoiceCommitment,
+    player2_choice: u8,
+    mode: ProofMode,
+) -> Result<(Vec<u8>, Vec<u8>, String)> {
+    let (nonce, choice) = choice_opening(config, account, commitment)?;
+
+    let rps_result_input = RpsResultInput {
+        client_pubkey: commitment.client_pubkey.clone(),
+        game_id: commitment.game_id,
+        choice_hash: commitment.choice_hash,
+        version: commitment.version,
+        nonce,
+        choice,
+        player2_choice,
+        secret: *account.secret(),
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&rps_result_input);
 
-    let proof_bytes = proof.bytes();
-    println!("proof: {proof_bytes:?}");
-    let public_values = proof.public_values.to_vec();
+    // Generate the proof
+    let proof = prover.prove(&programs::RPS_RESULT, &stdin, mode)?;
 
-    Ok((proof_bytes, public_values, vk.bytes32()))
+    Ok((
+        mode.encode(&proof)?,
+        proof.public_values.to_vec(),
+        programs::RPS_RESULT.vk_hash().to_string(),
+    ))
 }
 
-pub fn calculate_result(choice_1: u8, choice_2: u8) -> GameResult {
-    match (choice_1, choice_2) {
-        (1, 0) | (2, 1) | (0, 2) => GameResult::Player1,
-        (0, 1) | (1, 2) | (2, 0) => GameResult::Player2,
-        _ => GameResult::Draw,
+/// Make sure a proof was generated for the expected game and commitment
+fn check_game_binding(
+    proof_game_id: u64,
+    proof_client_pubkey: &str,
+    proof_choice_hash: &[u8; 32],
+    commitment: &ChoiceCommitment,
+) -> Result<()> {
+    if proof_game_id != commitment.game_id {
+        return Err(Error::InvalidPublicValues(format!(
+            "proof is for game {proof_game_id}, expected game {}",
+            commitment.game_id
+        )));
+    }
+    if proof_client_pubkey != commitment.client_pubkey {
+        return Err(Error::InvalidPublicValues(format!(
+            "proof is for client {proof_client_pubkey}, expected client {}",
+            commitment.client_pubkey
+        )));
     }
+    if *proof_choice_hash != commitment.choice_hash {
+        return Err(Error::InvalidPublicValues(
+            "proof doesn't match the game choice hash".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Verify a reveal proof against the rps-basic vk it was generated with, which the game
+/// must accept (see [`Game::check_vk`]), and make sure it reveals the choice of the expected game
+pub fn verify_basic_game_proof(
+    verifier: &ProofVerifier,
+    mode: ProofMode,
+    vk_hash: &str,
+    proof: &[u8],
+    public_values: &[u8],
+    commitment: &ChoiceCommitment,
+) -> Result<RpsBasicPublic> {
+    verifier.verify_encoded(&programs::RPS_BASIC, vk_hash, mode, proof, public_values)?;
+
+    let public_values = RpsBasicPublic::try_from(public_values)
+        .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+    check_game_binding(
+        public_values.game_id,
+        &public_values.client_pubkey,
+        &public_values.choice_hash,
+        commitment,
+    )?;
+
+    Ok(public_values)
+}
+
+/// Verify an aggregated proof against the pinned rps-aggregate vk and make sure
+/// it aggregates rps-basic reveals of the expected client
+pub fn verify_aggregate_proof(
+    verifier: &ProofVerifier,
+    mode: ProofMode,
+    proof: &[u8],
+    public_values: &[u8],
+    client_pubkey: &str,
+) -> Result<RpsAggregatePublic> {
+    verifier.verify_encoded(
+        &programs::RPS_AGGREGATE,
+        programs::RPS_AGGREGATE.vk_hash(),
+        mode,
+        proof,
+        public_values,
+    )?;
+
+    let public_values = RpsAggregatePublic::try_from(public_values)
+        .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+    if public_values.rps_basic_vkey != programs::RPS_BASIC.vk().hash_u32() {
+        return Err(Error::InvalidPublicValues(
+            "aggregated proofs don't come from the rps-basic program".to_string(),
+        ));
+    }
+    if public_values.client_pubkey != client_pubkey {
+        return Err(Error::InvalidPublicValues(format!(
+            "aggregated proof is for client {}, expected client {}",
+            public_values.client_pubkey, client_pubkey
+        )));
+    }
+
+    Ok(public_values)
+}
+
+/// Verify a result proof against the rps-result vk it was generated with, which the game
+/// must accept (see [`Game::check_vk`]), and make sure it settles the expected game
+/// against the choice of player 2
+pub fn verify_result_proof(
+    verifier: &ProofVerifier,
+    mode: ProofMode,
+    vk_hash: &str,
+    proof: &[u8],
+    public_values: &[u8],
+    commitment: &ChoiceCommitment,
+    player2_choice: u8,
+) -> Result<RpsResultPublic> {
+    verifier.verify_encoded(&programs::RPS_RESULT, vk_hash, mode, proof, public_values)?;
+
+    let public_values = RpsResultPublic::try_from(public_values)
+        .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+    check_game_binding(
+        public_values.game_id,
+        &public_values.client_pubkey,
+        &public_values.choice_hash,
+        commitment,
+    )?;
+    if public_values.player2_choice != player2_choice {
+        return Err(Error::InvalidPublicValues(format!(
+            "result proof is against choice {}, player2 chose {}",
+            public_values.player2_choice, player2_choice
+        )));
+    }
+
+    Ok(public_values)
 }
 
 // TODO: Save on chain the result
 
 #[cfg(test)]
 mod test {
-    use crate::games::rps_basic::Choice;
+    use std::fs;
+
+    use super::{
+        choice_opening, generate_basic_choice_hash, opening_path, ChoiceCommitment,
+        CHOICE_COMMITMENT_VERSION,
+    };
+    use crate::{testing::TestDir, Error};
 
     #[test]
-    fn test_results() {
-        // Test the player 1 wins when it should
-        assert_eq!(
-            super::calculate_result(Choice::Rock as u8, Choice::Scissors as u8),
-            super::GameResult::Player1
-        );
-        assert_eq!(
-            super::calculate_result(Choice::Paper as u8, Choice::Rock as u8),
-            super::GameResult::Player1
-        );
-        assert_eq!(
-            super::calculate_result(Choice::Scissors as u8, Choice::Paper as u8),
-            super::GameResult::Player1
-        );
+    fn test_choice_opening() {
+        let dir = TestDir::new("choice-opening");
+        let config = dir.config();
+        let (_, account) = dir.player("player");
+        let commitment = ChoiceCommitment {
+            client_pubkey: "client".to_string(),
+            game_id: 1,
+            choice_hash: generate_basic_choice_hash(&config, &account, "client", 1, 2).unwrap(),
+            version: CHOICE_COMMITMENT_VERSION,
+        };
 
-        // Test player 2 wins when it should
-        assert_eq!(
-            super::calculate_result(Choice::Scissors as u8, Choice::Rock as u8),
-            super::GameResult::Player2
-        );
-        assert_eq!(
-            super::calculate_result(Choice::Rock as u8, Choice::Paper as u8),
-            super::GameResult::Player2
-        );
-        assert_eq!(
-            super::calculate_result(Choice::Paper as u8, Choice::Scissors as u8),
-            super::GameResult::Player2
-        );
+        // The choice is read back with the nonce
+        let (nonce, choice) = choice_opening(&config, &account, &commitment).unwrap();
+        assert_eq!(choice, 2);
 
-        // Test draw
-        assert_eq!(
-            super::calculate_result(Choice::Rock as u8, Choice::Rock as u8),
-            super::GameResult::Draw
-        );
+        // Nonces saved before the choice still open the commitment
+        let path = opening_path(&config, "player", "client", 1);
+        fs::write(&path, nonce).unwrap();
         assert_eq!(
-            super::calculate_result(Choice::Paper as u8, Choice::Paper as u8),
-            super::GameResult::Draw
-        );
-        assert_eq!(
-            super::calculate_result(Choice::Scissors as u8, Choice::Scissors as u8),
-            super::GameResult::Draw
+            choice_opening(&config, &account, &commitment).unwrap(),
+            (nonce, 2)
         );
+
+        // A stored choice that doesn't open the commitment is refused before proving
+        fs::write(&path, [nonce.as_slice(), &[1]].concat()).unwrap();
+        assert!(matches!(
+            choice_opening(&config, &account, &commitment),
+            Err(Error::ChoiceNotFound(1))
+        ));
     }
 }
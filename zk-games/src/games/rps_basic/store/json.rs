@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{GameFilter, GameStore};
+use crate::{
+    clock::{Clock, SystemClock},
+    games::rps_basic::Game,
+    json_file::JsonFile,
+    Error, Result,
+};
+
+/// Layout of `games.json`
+#[derive(Serialize, Deserialize, Default)]
+struct GamesFile {
+    games: Vec<Game>,
+}
+
+/// Every game in a single JSON file, safe to share between client processes,
+/// see [`JsonFile`]
+pub struct JsonGameStore {
+    file: JsonFile,
+}
+
+impl JsonGameStore {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let store = JsonGameStore {
+            file: JsonFile::new(path, Error::CorruptGamesFile)?,
+        };
+
+        // Refuse to start on a corrupt file rather than overwrite it later
+        let games = store.snapshot()?;
+
+        // Games joined before deadlines get theirs once, from the first time they are loaded
+        if games.iter().any(Game::lacks_reveal_deadline) {
+            let now = SystemClock.now();
+            store.file.update(|file: &mut GamesFile| {
+                for game in &mut file.games {
+                    game.start_reveal_deadline(now);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(store)
+    }
+
+    fn snapshot(&self) -> Result<Vec<Game>> {
+        Ok(self.file.read::<GamesFile>()?.games)
+    }
+}
+
+impl GameStore for JsonGameStore {
+    fn create(&mut self, game: &Game) -> Result<()> {
+        self.file.update(|file: &mut GamesFile| {
+            if file.games.iter().any(|stored| stored.id == game.id) {
+                return Err(Error::GameExists(game.id));
+            }
+
+            file.games.push(game.clone());
+            file.games.sort_by_key(|game| game.id);
+            Ok(())
+        })
+    }
+
+    fn get(&self, id: u64) -> Result<Option<Game>> {
+        Ok(self.snapshot()?.into_iter().find(|game| game.id == id))
+    }
+
+    fn list(&self, filter: &GameFilter) -> Result<Vec<Game>> {
+        Ok(self
+            .snapshot()?
+            .into_iter()
+            .filter(|game| filter.matches(game))
+            .collect())
+    }
+
+    fn update(&mut self, previous: &Game, game: &Game) -> Result<()> {
+        self.file.update(|file: &mut GamesFile| {
+            let stored = file
+                .games
+                .iter_mut()
+                .find(|stored| stored.id == game.id)
+                .ok_or(Error::GameNotFound(game.id))?;
+            if stored != previous {
+                return Err(Error::GameConflict(game.id));
+            }
+
+            *stored = game.clone();
+            Ok(())
+        })
+    }
+
+    fn next_id(&self) -> Result<u64> {
+        Ok(self.snapshot()?.last().map_or(0, |game| game.id + 1))
+    }
+}
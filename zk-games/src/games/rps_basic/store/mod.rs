@@ -0,0 +1,285 @@
+mod json;
+mod sqlite;
+
+use std::path::Path;
+
+pub use json::JsonGameStore;
+pub use sqlite::SqliteGameStore;
+
+use super::{Game, GameStatus};
+use crate::{Config, Result};
+
+/// Extensions of `games_file` stored in SQLite, anything else is JSON
+pub const SQLITE_EXTENSIONS: [&str; 3] = ["db", "sqlite", "sqlite3"];
+
+/// Where games are kept, a game is read, goes through a transition and is updated back.
+/// Other clients may share the store, an update only applies to the game as it was read.
+pub trait GameStore {
+    /// Add a new game, its id must be free
+    fn create(&mut self, game: &Game) -> Result<()>;
+
+    fn get(&self, id: u64) -> Result<Option<Game>>;
+
+    /// Every game matching the filter, by id
+    fn list(&self, filter: &GameFilter) -> Result<Vec<Game>>;
+
+    /// Replace a stored game with its new state, fails if the stored game is no longer `previous`
+    fn update(&mut self, previous: &Game, game: &Game) -> Result<()>;
+
+    /// Id following every stored game
+    fn next_id(&self) -> Result<u64>;
+}
+
+/// Which games [`GameStore::list`] returns, every game when nothing is set
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GameFilter {
+    pub status: Option<GameStatus>,
+    /// Games where this user is player 1 or player 2
+    pub player: Option<String>,
+}
+
+impl GameFilter {
+    pub fn status(mut self, status: GameStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn player(mut self, username: impl Into<String>) -> Self {
+        self.player = Some(username.into());
+        self
+    }
+
+    pub fn matches(&self, game: &Game) -> bool {
+        let status = self.status.is_none_or(|status| game.status() == status);
+        let player = self.player.as_ref().is_none_or(|username| {
+            game.player1.username == *username
+                || game
+                    .player2()
+                    .is_some_and(|player2| player2.username == *username)
+        });
+
+        status && player
+    }
+}
+
+/// Open the store of `games_file`, SQLite or JSON depending on its extension
+pub fn open_game_store(config: &Config) -> Result<Box<dyn GameStore>> {
+    let is_sqlite = config
+        .games_file
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| SQLITE_EXTENSIONS.contains(&extension));
+
+    if is_sqlite {
+        Ok(Box::new(SqliteGameStore::open(&config.games_file)?))
+    } else {
+        Ok(Box::new(JsonGameStore::open(&config.games_file)?))
+    }
+}
+
+/// Copy every game of a `games.json` file into the store, games already there are kept.
+/// Returns how many games were imported.
+pub fn import_json_games(path: impl AsRef<Path>, store: &mut dyn GameStore) -> Result<usize> {
+    let source = JsonGameStore::open(path.as_ref())?;
+
+    let mut imported = 0;
+    for game in source.list(&GameFilter::default())? {
+        if store.get(game.id)?.is_none() {
+            store.create(&game)?;
+            imported += 1;
+        }
+    }
+
+    Ok(imported)
+}
+
+#[cfg(test)]
+mod test {
+    use zk_games_types::GameResult;
+
+    use super::{import_json_games, GameFilter, GameStore, JsonGameStore, SqliteGameStore};
+    use crate::{
+        games::rps_basic::{
+            Choice, Game, GameStatus, Player1Info, Player2Choice, Player2Info, TransitionError,
+        },
+        testing::TestDir,
+        Error,
+    };
+
+    fn new_game(id: u64, username: &str) -> Game {
+        Game::new(
+            id,
+            Player1Info {
+                username: username.to_string(),
+                choice_hash: [1; 32],
+                commitment_version: Default::default(),
+            },
+            100,
+        )
+    }
+
+    fn check_store(store: &mut dyn GameStore) {
+        assert_eq!(store.next_id().unwrap(), 0);
+
+        store.create(&new_game(0, "player1")).unwrap();
+        store.create(&new_game(1, "player3")).unwrap();
+        assert!(matches!(
+            store.create(&new_game(1, "player3")),
+            Err(Error::GameExists(1))
+        ));
+        assert_eq!(store.next_id().unwrap(), 2);
+
+        // Updated games are listed with their new status
+        let previous = store.get(0).unwrap().unwrap();
+        let mut game = previous.clone();
+        game.join(
+            Player2Info {
+                username: "player2".to_string(),
+                choice: Player2Choice::Plain(Choice::Rock),
+            },
+            0,
+        )
+        .unwrap();
+        store.update(&previous, &game).unwrap();
+        assert_eq!(store.get(0).unwrap(), Some(game.clone()));
+
+        // A change made from a stale copy is refused
+        let mut stale = previous.clone();
+        stale.cancel("player1").unwrap();
+        assert!(matches!(
+            store.update(&previous, &stale),
+            Err(Error::GameConflict(0))
+        ));
+        assert_eq!(store.get(0).unwrap(), Some(game));
+
+        let joined = store
+            .list(&GameFilter::default().status(GameStatus::Joined))
+            .unwrap();
+        assert_eq!(joined.len(), 1);
+        assert_eq!(joined[0].id, 0);
+
+        let player2 = store
+            .list(&GameFilter::default().player("player2"))
+            .unwrap();
+        assert_eq!(player2.len(), 1);
+        let open = store
+            .list(
+                &GameFilter::default()
+                    .status(GameStatus::Open)
+                    .player("player3"),
+            )
+            .unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, 1);
+        assert_eq!(store.list(&GameFilter::default()).unwrap().len(), 2);
+
+        assert!(store.get(2).unwrap().is_none());
+        assert!(matches!(
+            store.update(&new_game(2, "player1"), &new_game(2, "player1")),
+            Err(Error::GameNotFound(2))
+        ));
+    }
+
+    #[test]
+    fn test_stores() {
+        let test_dir = TestDir::new("game-stores");
+        let dir = test_dir.path();
+
+        check_store(&mut JsonGameStore::open(dir.join("games.json")).unwrap());
+        check_store(&mut SqliteGameStore::open(dir.join("games.db")).unwrap());
+
+        // Games are kept across opens, and ids are only taken once
+        let mut store = SqliteGameStore::open(dir.join("games.db")).unwrap();
+        assert_eq!(store.next_id().unwrap(), 2);
+        assert!(matches!(
+            store.create(&new_game(0, "player2")),
+            Err(Error::GameExists(0))
+        ));
+    }
+
+    #[test]
+    fn test_shared_json_file() {
+        let dir = TestDir::new("shared-games");
+        let path = dir.path().join("games.json");
+
+        // Two clients on the same file see each other's games
+        let mut client1 = JsonGameStore::open(&path).unwrap();
+        let mut client2 = JsonGameStore::open(&path).unwrap();
+        client1.create(&new_game(0, "player1")).unwrap();
+        assert_eq!(client2.next_id().unwrap(), 1);
+        client2.create(&new_game(1, "player2")).unwrap();
+        assert_eq!(client1.list(&GameFilter::default()).unwrap().len(), 2);
+
+        // A corrupt file is reported and left as is
+        std::fs::write(&path, b"{\"games\":[{\"id\":0,").unwrap();
+        assert!(matches!(
+            JsonGameStore::open(&path),
+            Err(Error::CorruptGamesFile(_))
+        ));
+        assert!(matches!(
+            client1.create(&new_game(2, "player1")),
+            Err(Error::CorruptGamesFile(_))
+        ));
+        assert_eq!(std::fs::read(&path).unwrap(), b"{\"games\":[{\"id\":0,");
+    }
+
+    #[test]
+    fn test_legacy_deadline() {
+        let dir = TestDir::new("legacy-deadline");
+        std::fs::create_dir_all(dir.path()).unwrap();
+        let path = dir.path().join("games.json");
+
+        // A game joined before deadlines, as saved by older clients
+        std::fs::write(
+            &path,
+            r#"{"games":[{"id":0,"player1":{"username":"player1","choice_hash":[1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1,1]},"player2":{"username":"player2","choice":"Rock"}}]}"#,
+        )
+        .unwrap();
+
+        // Its deadline is started on the first load and kept afterwards
+        let game = JsonGameStore::open(&path).unwrap().get(0).unwrap().unwrap();
+        let deadline = game.reveal_deadline().unwrap();
+        let mut store = JsonGameStore::open(&path).unwrap();
+        let reloaded = store.get(0).unwrap().unwrap();
+        assert_eq!(reloaded.reveal_deadline(), Some(deadline));
+
+        let mut claimed = reloaded.clone();
+        assert!(matches!(
+            claimed.claim_timeout("player2", deadline),
+            Err(TransitionError::DeadlineNotPassed(0))
+        ));
+        assert_eq!(
+            claimed.claim_timeout("player2", deadline + 1).unwrap(),
+            GameResult::Player2ByForfeit
+        );
+        store.update(&reloaded, &claimed).unwrap();
+        assert_eq!(
+            store.get(0).unwrap().unwrap().status(),
+            GameStatus::Forfeited
+        );
+    }
+
+    #[test]
+    fn test_import_json_games() {
+        let test_dir = TestDir::new("import-games");
+        let dir = test_dir.path();
+
+        let mut json = JsonGameStore::open(dir.join("games.json")).unwrap();
+        json.create(&new_game(0, "player1")).unwrap();
+        json.create(&new_game(1, "player1")).unwrap();
+
+        let mut sqlite = SqliteGameStore::open_in_memory().unwrap();
+        sqlite.create(&new_game(1, "player1")).unwrap();
+
+        // Only the missing game is imported, running it again is a no-op
+        assert_eq!(
+            import_json_games(dir.join("games.json"), &mut sqlite).unwrap(),
+            1
+        );
+        assert_eq!(
+            import_json_games(dir.join("games.json"), &mut sqlite).unwrap(),
+            0
+        );
+        assert_eq!(sqlite.list(&GameFilter::default()).unwrap().len(), 2);
+    }
+}
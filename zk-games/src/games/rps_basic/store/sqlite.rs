@@ -0,0 +1,169 @@
+use std::{fs, path::Path, time::Duration};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{GameFilter, GameStore};
+use crate::{
+    games::rps_basic::{Game, GameStatus},
+    Error, Result,
+};
+
+/// Games are stored as JSON, with the columns needed to filter them next to it
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS games (
+        id      INTEGER PRIMARY KEY,
+        status  TEXT NOT NULL,
+        player1 TEXT NOT NULL,
+        player2 TEXT,
+        game    TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS games_status ON games (status);
+    CREATE INDEX IF NOT EXISTS games_player1 ON games (player1);
+    CREATE INDEX IF NOT EXISTS games_player2 ON games (player2);
+";
+
+/// How long to wait for another client holding the database lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn storage_error(e: impl ToString) -> Error {
+    Error::Storage(e.to_string())
+}
+
+fn status_name(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::Open => "open",
+        GameStatus::Joined => "joined",
+        GameStatus::Completed => "completed",
+        GameStatus::Forfeited => "forfeited",
+        GameStatus::Cancelled => "cancelled",
+        GameStatus::Expired => "expired",
+    }
+}
+
+/// Games in a SQLite database, only the changed game is written
+pub struct SqliteGameStore {
+    conn: Connection,
+}
+
+impl SqliteGameStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        SqliteGameStore::init(Connection::open(path).map_err(storage_error)?)
+    }
+
+    /// A store that lives as long as the value, for tests
+    pub fn open_in_memory() -> Result<Self> {
+        SqliteGameStore::init(Connection::open_in_memory().map_err(storage_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(storage_error)?;
+        conn.execute_batch(SCHEMA).map_err(storage_error)?;
+        Ok(SqliteGameStore { conn })
+    }
+
+    /// Columns of a game row: status, player1, player2, game
+    fn columns(game: &Game) -> Result<(&'static str, &str, Option<&str>, String)> {
+        Ok((
+            status_name(game.status()),
+            game.player1.username.as_str(),
+            game.player2().map(|player2| player2.username.as_str()),
+            serde_json::to_string(game).map_err(storage_error)?,
+        ))
+    }
+}
+
+impl GameStore for SqliteGameStore {
+    fn create(&mut self, game: &Game) -> Result<()> {
+        let (status, player1, player2, json) = SqliteGameStore::columns(game)?;
+        // Another client may have created it since we picked the id
+        let inserted = self
+            .conn
+            .execute(
+                "INSERT INTO games (id, status, player1, player2, game) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (id) DO NOTHING",
+                params![game.id, status, player1, player2, json],
+            )
+            .map_err(storage_error)?;
+
+        if inserted == 0 {
+            return Err(Error::GameExists(game.id));
+        }
+        Ok(())
+    }
+
+    fn get(&self, id: u64) -> Result<Option<Game>> {
+        let json: Option<String> = self
+            .conn
+            .query_row("SELECT game FROM games WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(storage_error)?;
+
+        json.map(|json| serde_json::from_str(&json).map_err(storage_error))
+            .transpose()
+    }
+
+    fn list(&self, filter: &GameFilter) -> Result<Vec<Game>> {
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT game FROM games
+                 WHERE (?1 IS NULL OR status = ?1)
+                   AND (?2 IS NULL OR player1 = ?2 OR player2 = ?2)
+                 ORDER BY id",
+            )
+            .map_err(storage_error)?;
+
+        let rows = statement
+            .query_map(
+                params![filter.status.map(status_name), filter.player],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(storage_error)?;
+
+        let games = rows
+            .map(|json| {
+                let json = json.map_err(storage_error)?;
+                serde_json::from_str(&json).map_err(storage_error)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(games)
+    }
+
+    fn update(&mut self, previous: &Game, game: &Game) -> Result<()> {
+        let (status, player1, player2, json) = SqliteGameStore::columns(game)?;
+        let previous = serde_json::to_string(previous).map_err(storage_error)?;
+        let updated = self
+            .conn
+            .execute(
+                "UPDATE games SET status = ?2, player1 = ?3, player2 = ?4, game = ?5
+                 WHERE id = ?1 AND game = ?6",
+                params![game.id, status, player1, player2, json, previous],
+            )
+            .map_err(storage_error)?;
+
+        if updated == 0 {
+            return match self.get(game.id)? {
+                Some(_) => Err(Error::GameConflict(game.id)),
+                None => Err(Error::GameNotFound(game.id)),
+            };
+        }
+        Ok(())
+    }
+
+    fn next_id(&self) -> Result<u64> {
+        let max_id: Option<u64> = self
+            .conn
+            .query_row("SELECT MAX(id) FROM games", [], |row| row.get(0))
+            .map_err(storage_error)?;
+
+        Ok(max_id.map_or(0, |id| id + 1))
+    }
+}
@@ -0,0 +1,114 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use fs2::FileExt;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Error, Result};
+
+/// Write a JSON document to a temporary sibling first, then move it over `path`.
+/// Readers see the old file or the new one, never a partial write.
+pub fn write_json_atomic<T: Serialize>(path: &Path, document: &T) -> Result<()> {
+    // Unique, concurrent writers of the same path don't write into each other
+    let mut tmp_path = OsString::from(path.as_os_str());
+    tmp_path.push(format!(
+        ".{}-{:x}.tmp",
+        std::process::id(),
+        rand::random::<u64>()
+    ));
+
+    let file = File::create(&tmp_path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, document).map_err(std::io::Error::from)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+/// A JSON document safe to share between processes.
+/// Every change reads the file again under an advisory lock,
+/// and replaces it atomically so readers never see a partial write.
+pub struct JsonFile {
+    path: PathBuf,
+    /// Error reported when the file can't be parsed
+    corrupt: fn(String) -> Error,
+}
+
+impl JsonFile {
+    pub fn new(path: impl Into<PathBuf>, corrupt: fn(String) -> Error) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(JsonFile { path, corrupt })
+    }
+
+    /// Sibling of the file, `<file><suffix>`
+    fn sibling(&self, suffix: &str) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(suffix);
+        PathBuf::from(path)
+    }
+
+    /// Lock a separate file, the document itself is replaced on every write.
+    /// The lock is released when the returned file is dropped.
+    fn lock(&self, exclusive: bool) -> Result<File> {
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.sibling(".lock"))?;
+
+        if exclusive {
+            lock.lock_exclusive()?;
+        } else {
+            lock.lock_shared()?;
+        }
+        Ok(lock)
+    }
+
+    /// A missing or empty file is the default document
+    fn read_unlocked<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(T::default()),
+            Err(e) => return Err(e.into()),
+        };
+        if bytes.is_empty() {
+            return Ok(T::default());
+        }
+
+        serde_json::from_slice(&bytes)
+            .map_err(|e| (self.corrupt)(format!("{}: {e}", self.path.display())))
+    }
+
+    fn write_unlocked<T: Serialize>(&self, document: &T) -> Result<()> {
+        write_json_atomic(&self.path, document)
+    }
+
+    pub fn read<T: DeserializeOwned + Default>(&self) -> Result<T> {
+        let _lock = self.lock(false)?;
+        self.read_unlocked()
+    }
+
+    /// Apply a change to the latest document on disk, nobody else can write meanwhile.
+    /// Nothing is written if the change fails.
+    pub fn update<T, R>(&self, change: impl FnOnce(&mut T) -> Result<R>) -> Result<R>
+    where
+        T: Serialize + DeserializeOwned + Default,
+    {
+        let _lock = self.lock(true)?;
+
+        let mut document = self.read_unlocked()?;
+        let res = change(&mut document)?;
+        self.write_unlocked(&document)?;
+        Ok(res)
+    }
+}
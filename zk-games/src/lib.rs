@@ -1,5 +1,20 @@
+pub mod clock;
+pub mod config;
+pub mod error;
 pub mod games;
+pub mod json_file;
+pub mod login;
+pub mod programs;
+pub mod proof_store;
+pub mod prover;
+pub mod registry;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod user;
+pub mod verifier;
 pub mod zk;
 
-pub const PROOFS_PATH: &str = "/mnt/extra/Projects/solana/zk-games/data/proofs";
+pub use config::Config;
+pub use error::{Error, Result};
+pub use prover::ProverBackend;
+pub use verifier::ProofVerifier;
@@ -0,0 +1,256 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use rand::{distr::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use sp1_sdk::SP1ProofWithPublicValues;
+use zk_games_types::LoginPublic;
+
+use crate::{
+    clock::{Clock, SystemClock},
+    json_file::JsonFile,
+    programs,
+    proof_store::{self, ProofKey},
+    prover::ProofMode,
+    registry::get_public_account,
+    Config, Error, ProofVerifier, Result,
+};
+
+/// How long a login challenge can be answered, in seconds
+pub const LOGIN_CHALLENGE_TTL: u64 = 5 * 60;
+pub const LOGIN_CHALLENGE_LEN: usize = 32;
+/// Challenges issued by the registry, in the public players dir
+pub const LOGIN_CHALLENGES_FILENAME: &str = "login-challenges.json";
+
+/// A login attempt issued by the verifier, the prover must commit to all of it
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LoginChallenge {
+    pub username: String,
+    pub random_string: String,
+    /// The client the login is for (audience)
+    pub client_pubkey: String,
+    pub expires_at: u64,
+}
+
+impl LoginChallenge {
+    /// A fresh challenge with a random string nobody can predict
+    pub fn new(username: &str, client_pubkey: &str, expires_at: u64) -> Self {
+        let random_string: String = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(LOGIN_CHALLENGE_LEN)
+            .map(char::from)
+            .collect();
+
+        LoginChallenge {
+            username: username.to_string(),
+            random_string,
+            client_pubkey: client_pubkey.to_string(),
+            expires_at,
+        }
+    }
+}
+
+/// Check a login proof answers the challenge for `login_hash`: audience, expiry, proof
+/// and committed values. Keeping track of issued and answered challenges is up to the caller.
+pub fn check_login_answer(
+    verifier: &ProofVerifier,
+    proof: &SP1ProofWithPublicValues,
+    public_values: LoginPublic,
+    challenge: &LoginChallenge,
+    login_hash: [u8; 32],
+    now: u64,
+) -> Result<()> {
+    // The proof must be meant for this client and still be valid
+    if public_values.client_pubkey != challenge.client_pubkey {
+        return Err(Error::WrongAudience(public_values.client_pubkey));
+    }
+    if now > challenge.expires_at.min(public_values.expires_at) {
+        return Err(Error::ChallengeExpired);
+    }
+
+    verifier.verify(proof, &programs::LOGIN)?;
+
+    if public_values.login_hash != login_hash
+        || public_values.random_string != challenge.random_string
+        || public_values.username != challenge.username
+        || public_values.expires_at != challenge.expires_at
+    {
+        return Err(Error::InvalidPublicValues(format!(
+            "login proof doesn't match the challenge of {}",
+            challenge.username
+        )));
+    }
+
+    Ok(())
+}
+
+/// Issued and presented login challenges, each challenge can only be answered once.
+/// Expired challenges can't be answered anymore, they are dropped.
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct LoginChallenges {
+    /// Issued challenges by random string
+    pending: BTreeMap<String, LoginChallenge>,
+    /// Expiry of the random strings already presented
+    consumed: BTreeMap<String, u64>,
+}
+
+impl LoginChallenges {
+    pub fn issue(
+        &mut self,
+        username: &str,
+        client_pubkey: &str,
+        expires_at: u64,
+        now: u64,
+    ) -> LoginChallenge {
+        self.prune(now);
+
+        let challenge = LoginChallenge::new(username, client_pubkey, expires_at);
+        self.pending
+            .insert(challenge.random_string.clone(), challenge.clone());
+        challenge
+    }
+
+    /// Consume the challenge a login answers, the first time it is presented
+    /// whatever the outcome of the login
+    pub fn take(&mut self, random_string: &str, now: u64) -> Result<LoginChallenge> {
+        if self.consumed.contains_key(random_string) {
+            return Err(Error::ChallengeReplayed);
+        }
+        let challenge = self
+            .pending
+            .remove(random_string)
+            .ok_or(Error::UnknownChallenge)?;
+        self.consumed
+            .insert(challenge.random_string.clone(), challenge.expires_at);
+
+        self.prune(now);
+        Ok(challenge)
+    }
+
+    fn prune(&mut self, now: u64) {
+        self.pending
+            .retain(|_, challenge| now <= challenge.expires_at);
+        self.consumed.retain(|_, expires_at| now <= *expires_at);
+    }
+}
+
+/// Checks login proofs on behalf of the registry, so the prover never vouches for itself.
+/// Challenges are kept next to the public accounts, shared by every process checking logins.
+pub struct LoginVerifier {
+    config: Config,
+    verifier: ProofVerifier,
+    /// Only proofs meant for this client are accepted
+    client_pubkey: String,
+    clock: Arc<dyn Clock>,
+    ttl: u64,
+    challenges: JsonFile,
+}
+
+impl LoginVerifier {
+    pub fn new(config: Config, client_pubkey: &str) -> Result<Self> {
+        let challenges = JsonFile::new(
+            config.public_players_dir.join(LOGIN_CHALLENGES_FILENAME),
+            Error::CorruptChallenges,
+        )?;
+
+        Ok(LoginVerifier {
+            config,
+            verifier: ProofVerifier::Strict,
+            client_pubkey: client_pubkey.to_string(),
+            clock: Arc::new(SystemClock),
+            ttl: LOGIN_CHALLENGE_TTL,
+            challenges,
+        })
+    }
+
+    /// Only tests running the mock prover need another verifier than [`ProofVerifier::Strict`]
+    pub fn with_verifier(mut self, verifier: ProofVerifier) -> Self {
+        self.verifier = verifier;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_ttl(mut self, ttl: u64) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn issue_challenge(&self, username: &str) -> Result<LoginChallenge> {
+        let now = self.clock.now();
+        self.challenges.update(|challenges: &mut LoginChallenges| {
+            Ok(challenges.issue(username, &self.client_pubkey, now + self.ttl, now))
+        })
+    }
+
+    /// Verify a login proof against an issued challenge, returns the verified username
+    pub fn verify(&self, proof: &SP1ProofWithPublicValues) -> Result<String> {
+        let public_values = LoginPublic::try_from(proof.public_values.as_slice())
+            .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+
+        // The challenge is consumed on disk even if the login fails
+        let now = self.clock.now();
+        let challenge = self
+            .challenges
+            .update(|challenges: &mut LoginChallenges| {
+                Ok(challenges.take(&public_values.random_string, now))
+            })??;
+
+        let account = get_public_account(&self.config, &challenge.username)?;
+        check_login_answer(
+            &self.verifier,
+            proof,
+            public_values,
+            &challenge,
+            account.login_hash,
+            now,
+        )?;
+
+        // Keep the accepted proof, so the login can be audited later
+        let mode = ProofMode::of(proof);
+        proof_store::save_proof(
+            &self.config,
+            ProofKey::Login(challenge.random_string),
+            &programs::LOGIN,
+            programs::LOGIN.vk_hash(),
+            mode,
+            &mode.encode(proof)?,
+            proof.public_values.as_slice(),
+        )?;
+
+        Ok(challenge.username)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::LoginChallenges;
+    use crate::Error;
+
+    #[test]
+    fn test_challenges_are_pruned() {
+        let mut challenges = LoginChallenges::default();
+        let first = challenges.issue("player", "client", 10, 0);
+        let second = challenges.issue("player", "client", 20, 0);
+
+        assert_eq!(challenges.take(&first.random_string, 5).unwrap(), first);
+        assert!(matches!(
+            challenges.take(&first.random_string, 5),
+            Err(Error::ChallengeReplayed)
+        ));
+        assert!(matches!(
+            challenges.take("made-up", 5),
+            Err(Error::UnknownChallenge)
+        ));
+
+        // Nothing expired is kept
+        challenges.issue("player", "client", 30, 15);
+        assert!(!challenges.consumed.contains_key(&first.random_string));
+        assert!(challenges.pending.contains_key(&second.random_string));
+        challenges.issue("player", "client", 40, 25);
+        assert!(!challenges.pending.contains_key(&second.random_string));
+        assert_eq!(challenges.pending.len(), 2);
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::OnceLock;
+
+use sp1_sdk::{include_elf, HashableKey, SP1ProvingKey, SP1VerifyingKey};
+
+use crate::prover::ProverBackend;
+
+/// Proves knowledge of the secret behind a login hash
+pub static LOGIN: Program = Program::new("login-zk-program", include_elf!("login-zk-program"));
+
+/// Proves knowledge of the old secret to replace a login hash
+pub static ROTATE: Program = Program::new("rotate-zk-program", include_elf!("rotate-zk-program"));
+
+/// Reveals the choice behind a rps-basic choice hash
+pub static RPS_BASIC: Program =
+    Program::new("rps-basic-zk-program", include_elf!("rps-basic-zk-program"));
+
+/// Settles a rps-basic game, the result is computed inside the program
+pub static RPS_RESULT: Program = Program::new(
+    "rps-result-zk-program",
+    include_elf!("rps-result-zk-program"),
+);
+
+/// Verifies many rps-basic reveals at once, to settle them with a single proof
+pub static RPS_AGGREGATE: Program = Program::new(
+    "rps-aggregate-zk-program",
+    include_elf!("rps-aggregate-zk-program"),
+);
+
+/// Every program known to zk-games
+pub fn all() -> [&'static Program; 5] {
+    [&LOGIN, &ROTATE, &RPS_BASIC, &RPS_RESULT, &RPS_AGGREGATE]
+}
+
+/// Find a program by its vk hash
+pub fn by_vk_hash(vk_hash: &str) -> Option<&'static Program> {
+    all()
+        .into_iter()
+        .find(|program| program.vk_hash() == vk_hash)
+}
+
+/// Find a program by its name
+pub fn by_name(name: &str) -> Option<&'static Program> {
+    all().into_iter().find(|program| program.name == name)
+}
+
+/// A guest program embedded at build time, its keys are generated once on first use
+pub struct Program {
+    pub name: &'static str,
+    pub elf: &'static [u8],
+    keys: OnceLock<(SP1ProvingKey, SP1VerifyingKey)>,
+    vk_hash: OnceLock<String>,
+}
+
+impl Program {
+    const fn new(name: &'static str, elf: &'static [u8]) -> Self {
+        Program {
+            name,
+            elf,
+            keys: OnceLock::new(),
+            vk_hash: OnceLock::new(),
+        }
+    }
+
+    fn keys(&self) -> &(SP1ProvingKey, SP1VerifyingKey) {
+        // Keys only depend on the ELF, so any backend gives the same result
+        self.keys
+            .get_or_init(|| ProverBackend::Mock.setup(self.elf))
+    }
+
+    pub fn pk(&self) -> &SP1ProvingKey {
+        &self.keys().0
+    }
+
+    pub fn vk(&self) -> &SP1VerifyingKey {
+        &self.keys().1
+    }
+
+    /// `bytes32` digest of the verifying key, identifies this exact program version
+    pub fn vk_hash(&self) -> &str {
+        self.vk_hash.get_or_init(|| self.vk().bytes32())
+    }
+}
@@ -0,0 +1,189 @@
+use std::{fmt::Write, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    json_file::write_json_atomic,
+    programs::{self, Program},
+    prover::ProofMode,
+    Config, Error, ProofVerifier, Result,
+};
+
+pub const GAMES_DIRNAME: &str = "games";
+pub const LOGINS_DIRNAME: &str = "logins";
+
+/// What a stored proof is about
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ProofKey {
+    /// Any proof settling or revealing in a game
+    Game(u64),
+    /// A login proof, by the random string of its challenge
+    Login(String),
+}
+
+impl ProofKey {
+    fn dir(&self, config: &Config) -> PathBuf {
+        match self {
+            ProofKey::Game(id) => config.proofs_dir.join(GAMES_DIRNAME).join(id.to_string()),
+            ProofKey::Login(random_string) => {
+                config.proofs_dir.join(LOGINS_DIRNAME).join(random_string)
+            }
+        }
+    }
+}
+
+/// A proof as it was accepted, enough to verify it again later without re-proving
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProofRecord {
+    pub key: ProofKey,
+    /// Name of the program that generated the proof
+    pub program: String,
+    /// `bytes32` digest of the vk the proof was verified against
+    pub vk_hash: String,
+    /// Proof system of the proof, tells how to check it
+    pub mode: ProofMode,
+    /// Proof encoded as [`ProofMode::encode`] does, SNARKs are empty for mock proofs
+    pub proof: Vec<u8>,
+    pub public_values: Vec<u8>,
+    /// Unix seconds
+    pub created_at: u64,
+    /// Hex `sha256` of everything above but the timestamp, also the record file name
+    pub content_hash: String,
+}
+
+impl ProofRecord {
+    fn compute_hash(
+        program: &str,
+        vk_hash: &str,
+        mode: ProofMode,
+        proof: &[u8],
+        public_values: &[u8],
+    ) -> String {
+        let mode = [mode as u8];
+        let mut hasher = Sha256::new();
+        // Length prefixes, so fields can't be shifted into one another
+        for field in [
+            program.as_bytes(),
+            vk_hash.as_bytes(),
+            &mode,
+            proof,
+            public_values,
+        ] {
+            hasher.update((field.len() as u64).to_le_bytes());
+            hasher.update(field);
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+
+    /// Check the record wasn't altered and its proof holds against the vk it was accepted with,
+    /// which must be the one of our build of the program or one of `accepted_vks`
+    pub fn verify(
+        &self,
+        verifier: &ProofVerifier,
+        accepted_vks: &[String],
+    ) -> Result<&'static Program> {
+        let content_hash = Self::compute_hash(
+            &self.program,
+            &self.vk_hash,
+            self.mode,
+            &self.proof,
+            &self.public_values,
+        );
+        if content_hash != self.content_hash {
+            return Err(Error::CorruptProof(format!(
+                "content hash of {} doesn't match",
+                self.content_hash
+            )));
+        }
+
+        let program = programs::by_name(&self.program)
+            .ok_or_else(|| Error::CorruptProof(format!("unknown program {}", self.program)))?;
+        if program.vk_hash() != self.vk_hash && !accepted_vks.contains(&self.vk_hash) {
+            return Err(Error::Verification(format!(
+                "proof was generated by an unknown version of {}",
+                self.program
+            )));
+        }
+
+        verifier.verify_encoded(
+            program,
+            &self.vk_hash,
+            self.mode,
+            &self.proof,
+            &self.public_values,
+        )?;
+        Ok(program)
+    }
+}
+
+/// Store a proof of the program and the vk it was verified against under the given key,
+/// storing the same proof twice is a no-op
+pub fn save_proof(
+    config: &Config,
+    key: ProofKey,
+    program: &Program,
+    vk_hash: &str,
+    mode: ProofMode,
+    proof: &[u8],
+    public_values: &[u8],
+) -> Result<ProofRecord> {
+    let record = ProofRecord {
+        content_hash: ProofRecord::compute_hash(program.name, vk_hash, mode, proof, public_values),
+        key,
+        program: program.name.to_string(),
+        vk_hash: vk_hash.to_string(),
+        mode,
+        proof: proof.to_vec(),
+        public_values: public_values.to_vec(),
+        created_at: SystemClock.now(),
+    };
+
+    let dir = record.key.dir(config);
+    fs::create_dir_all(&dir)?;
+
+    // A record only shows up complete, an interrupted save is done again next time
+    let path = dir.join(format!("{}.json", record.content_hash));
+    if !path.exists() {
+        write_json_atomic(&path, &record)?;
+    }
+
+    Ok(record)
+}
+
+/// Every proof stored under the key, oldest first
+pub fn load_proofs(config: &Config, key: &ProofKey) -> Result<Vec<ProofRecord>> {
+    let dir = key.dir(config);
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let paths = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut records = paths
+        .into_iter()
+        // Skip what an interrupted save left behind
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .map(|path| {
+            let file = fs::File::open(&path)?;
+            serde_json::from_reader::<_, ProofRecord>(file)
+                .map_err(|e| Error::CorruptProof(format!("{}: {e}", path.display())))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    records.sort_by_key(|record| record.created_at);
+    Ok(records)
+}
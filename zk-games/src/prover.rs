@@ -0,0 +1,182 @@
+use std::env;
+
+use serde::{Deserialize, Serialize};
+use sp1_sdk::{
+    CpuProver, ExecutionReport, NetworkProver, Prover, ProverClient, SP1Proof,
+    SP1ProofWithPublicValues, SP1ProvingKey, SP1PublicValues, SP1Stdin, SP1VerifyingKey,
+};
+
+use crate::{programs::Program, Error, Result};
+
+/// Env var selecting the backend: `mock`, `cpu` or `network`.
+/// Mock proofs are rejected by every [`crate::ProofVerifier`] but the test one.
+pub const PROVER_ENV: &str = "ZK_GAMES_PROVER";
+/// Private key of the Succinct network account
+pub const NETWORK_PK_ENV: &str = "SP1_NETWORK_PK";
+/// Optional RPC url of the Succinct network
+pub const NETWORK_RPC_ENV: &str = "SP1_NETWORK_RPC";
+pub const DEFAULT_NETWORK_RPC: &str = "https://rpc.production.succinct.xyz";
+
+/// Where proofs are generated, every proving call goes through this.
+/// Checking proofs is up to [`crate::ProofVerifier`], whatever the backend.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProverBackend {
+    /// Fake proofs, fast and offline, for dev and tests
+    Mock,
+    /// Real proofs generated on the local CPU
+    Cpu,
+    /// Real proofs generated by the Succinct prover network
+    Network {
+        private_key: String,
+        rpc_url: String,
+    },
+}
+
+/// Which proof system a proof is generated with
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofMode {
+    /// STARK proof of every shard, the fastest to generate, only checked off-chain
+    Core,
+    /// STARK proof of constant size, only checked off-chain or inside another program
+    Compressed,
+    /// SNARK small enough to be checked on-chain
+    Plonk,
+    /// SNARK small enough to be checked on-chain, the cheapest to verify
+    #[default]
+    Groth16,
+}
+
+impl ProofMode {
+    /// Mode a proof was generated with
+    pub fn of(proof: &SP1ProofWithPublicValues) -> Self {
+        match proof.proof {
+            SP1Proof::Core(_) => ProofMode::Core,
+            SP1Proof::Compressed(_) => ProofMode::Compressed,
+            SP1Proof::Plonk(_) => ProofMode::Plonk,
+            SP1Proof::Groth16(_) => ProofMode::Groth16,
+        }
+    }
+
+    /// Encode a proof of this mode, to be stored or sent to a verifier.
+    /// SNARKs are their on-chain bytes, STARKs the whole proof with its public values.
+    pub fn encode(&self, proof: &SP1ProofWithPublicValues) -> Result<Vec<u8>> {
+        match self {
+            ProofMode::Plonk | ProofMode::Groth16 => Ok(proof.bytes()),
+            ProofMode::Core | ProofMode::Compressed => {
+                bincode::serialize(proof).map_err(|e| Error::Proving(e.to_string()))
+            }
+        }
+    }
+}
+
+/// The concrete SP1 client behind a backend
+enum BackendClient {
+    Cpu(CpuProver),
+    Network(NetworkProver),
+}
+
+impl ProverBackend {
+    /// Select the backend from `ZK_GAMES_PROVER`, defaults to cpu
+    pub fn from_env() -> Result<Self> {
+        match env::var(PROVER_ENV)
+            .unwrap_or_else(|_| "cpu".to_string())
+            .to_lowercase()
+            .as_str()
+        {
+            "mock" => Ok(ProverBackend::Mock),
+            "cpu" | "local" => Ok(ProverBackend::Cpu),
+            "network" => ProverBackend::network_from_env(),
+            other => Err(Error::ProverConfig(format!(
+                "unknown prover backend \"{other}\", expected one of: mock, cpu, network"
+            ))),
+        }
+    }
+
+    /// Network backend with credentials from `SP1_NETWORK_PK` and `SP1_NETWORK_RPC`
+    pub fn network_from_env() -> Result<Self> {
+        let private_key = env::var(NETWORK_PK_ENV).map_err(|_| {
+            Error::ProverConfig(format!(
+                "{NETWORK_PK_ENV} must be set to generate proofs on the prover network"
+            ))
+        })?;
+        let rpc_url = env::var(NETWORK_RPC_ENV).unwrap_or_else(|_| DEFAULT_NETWORK_RPC.to_string());
+
+        Ok(ProverBackend::Network {
+            private_key,
+            rpc_url,
+        })
+    }
+
+    fn client(&self) -> BackendClient {
+        match self {
+            ProverBackend::Mock => BackendClient::Cpu(ProverClient::builder().mock().build()),
+            ProverBackend::Cpu => BackendClient::Cpu(ProverClient::builder().cpu().build()),
+            ProverBackend::Network {
+                private_key,
+                rpc_url,
+            } => BackendClient::Network(
+                ProverClient::builder()
+                    .network()
+                    .private_key(private_key)
+                    .rpc_url(rpc_url)
+                    .build(),
+            ),
+        }
+    }
+
+    /// Generate the proving and verifying keys of a program
+    pub fn setup(&self, elf: &[u8]) -> (SP1ProvingKey, SP1VerifyingKey) {
+        match self.client() {
+            BackendClient::Cpu(client) => client.setup(elf),
+            BackendClient::Network(client) => client.setup(elf),
+        }
+    }
+
+    /// Run the program without proving, the report holds its cycle counts
+    pub fn execute(
+        &self,
+        program: &Program,
+        stdin: &SP1Stdin,
+    ) -> Result<(SP1PublicValues, ExecutionReport)> {
+        // Execution always happens locally, whatever the backend
+        ProverClient::builder()
+            .mock()
+            .build()
+            .execute(program.elf, stdin)
+            .run()
+            .map_err(|e| Error::Execution(e.to_string()))
+    }
+
+    /// Generate a proof of the program with the given input
+    pub fn prove(
+        &self,
+        program: &Program,
+        stdin: &SP1Stdin,
+        mode: ProofMode,
+    ) -> Result<SP1ProofWithPublicValues> {
+        let pk = program.pk();
+        let proof = match self.client() {
+            BackendClient::Cpu(client) => {
+                let request = client.prove(pk, stdin);
+                match mode {
+                    ProofMode::Core => request.core().run(),
+                    ProofMode::Compressed => request.compressed().run(),
+                    ProofMode::Plonk => request.plonk().run(),
+                    ProofMode::Groth16 => request.groth16().run(),
+                }
+            }
+            BackendClient::Network(client) => {
+                let request = client.prove(pk, stdin);
+                match mode {
+                    ProofMode::Core => request.core().run(),
+                    ProofMode::Compressed => request.compressed().run(),
+                    ProofMode::Plonk => request.plonk().run(),
+                    ProofMode::Groth16 => request.groth16().run(),
+                }
+            }
+        };
+
+        proof.map_err(|e| Error::Proving(e.to_string()))
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use sp1_sdk::SP1ProofWithPublicValues;
+use zk_games_types::RotatePublic;
+
+use crate::{programs, user::AccountKdf, Config, Error, ProofVerifier, Result};
+
+// TODO: Save the public accounts on Solana
+pub const ACCOUNT_FILENAME: &str = "account.json";
+/// Accounts registered before the account record only published their login hash
+pub const LOGIN_HASH_FILENAME: &str = "login-hash";
+
+/// Public record of a player, everything needed to check a login
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PublicAccount {
+    pub username: String,
+    /// How the secret is derived from the password
+    pub kdf: AccountKdf,
+    /// `sha256(secret)`
+    pub login_hash: [u8; 32],
+}
+
+/// Publish the record of a new account, changing it later takes a rotation proof
+pub fn register_public_account(config: &Config, account: &PublicAccount) -> Result<()> {
+    match get_public_account(config, &account.username) {
+        Err(Error::AccountNotFound(_)) => save_public_account(config, account),
+        Ok(_) => Err(Error::AccountExists(account.username.clone())),
+        Err(e) => Err(e),
+    }
+}
+
+pub(crate) fn save_public_account(config: &Config, account: &PublicAccount) -> Result<()> {
+    let user_public_path = config.public_players_dir.join(&account.username);
+
+    if !user_public_path.exists() {
+        fs::create_dir_all(&user_public_path)?;
+    }
+
+    let file = fs::File::create(user_public_path.join(ACCOUNT_FILENAME))?;
+    serde_json::to_writer(file, account).map_err(std::io::Error::from)?;
+
+    // The account record replaces the legacy login hash
+    let legacy_path = user_public_path.join(LOGIN_HASH_FILENAME);
+    if legacy_path.exists() {
+        fs::remove_file(legacy_path)?;
+    }
+
+    Ok(())
+}
+
+pub fn get_public_account(config: &Config, username: &str) -> Result<PublicAccount> {
+    let user_public_path = config.public_players_dir.join(username);
+
+    let account_path = user_public_path.join(ACCOUNT_FILENAME);
+    if account_path.exists() {
+        let file = fs::File::open(account_path)?;
+        return serde_json::from_reader(file)
+            .map_err(|_| Error::CorruptAccount(username.to_string()));
+    }
+
+    let legacy_path = user_public_path.join(LOGIN_HASH_FILENAME);
+    if legacy_path.exists() {
+        let login_hash = fs::read(legacy_path)?
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::CorruptAccount(username.to_string()))?;
+
+        return Ok(PublicAccount {
+            username: username.to_string(),
+            kdf: AccountKdf::Legacy,
+            login_hash,
+        });
+    }
+
+    Err(Error::AccountNotFound(username.to_string()))
+}
+
+/// Replace the public record of an account, only accepted with a proof
+/// of the secret behind the currently registered login hash
+pub fn rotate_public_account(
+    config: &Config,
+    verifier: &ProofVerifier,
+    new_account: &PublicAccount,
+    proof: &SP1ProofWithPublicValues,
+) -> Result<()> {
+    verifier.verify(proof, &programs::ROTATE)?;
+
+    let public_values = RotatePublic::try_from(proof.public_values.as_slice())
+        .map_err(|e| Error::InvalidPublicValues(e.to_string()))?;
+    let current_account = get_public_account(config, &new_account.username)?;
+
+    if public_values.username != new_account.username {
+        return Err(Error::InvalidPublicValues(format!(
+            "rotation proof is for {}, expected {}",
+            public_values.username, new_account.username
+        )));
+    }
+    if public_values.old_login_hash != current_account.login_hash {
+        return Err(Error::InvalidPublicValues(
+            "rotation proof doesn't match the registered login hash".to_string(),
+        ));
+    }
+    if public_values.new_login_hash != new_account.login_hash {
+        return Err(Error::InvalidPublicValues(
+            "rotation proof doesn't match the new login hash".to_string(),
+        ));
+    }
+    // Without it anyone replaying the proof could publish a kdf the owner can't derive with
+    if public_values.new_kdf_hash != new_account.kdf.hash() {
+        return Err(Error::InvalidPublicValues(
+            "rotation proof doesn't match the new kdf".to_string(),
+        ));
+    }
+
+    save_public_account(config, new_account)
+}
@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    registry::{register_public_account, PublicAccount},
+    user::{create_account, Account},
+    Config,
+};
+
+/// Password of every player made by [`TestDir::player`]
+pub const TEST_PASSWORD: &str = "some_password1234";
+
+/// Data dir of a single test, unique so tests can run in parallel.
+/// Removed when dropped, whether the test passed or not.
+pub struct TestDir {
+    path: PathBuf,
+}
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "zk-games-test-{name}-{}-{:x}",
+            std::process::id(),
+            rand::random::<u64>()
+        ));
+
+        TestDir { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every path of the config is under this dir
+    pub fn config(&self) -> Config {
+        Config::from_data_dir(&self.path)
+    }
+
+    /// Create, register and unlock a player with [`TEST_PASSWORD`]
+    pub fn player(&self, username: &str) -> (PublicAccount, Account) {
+        let config = self.config();
+        let public_account = create_account(&config, username, TEST_PASSWORD).unwrap();
+        register_public_account(&config, &public_account).unwrap();
+        let account = Account::unlock(&config, username, TEST_PASSWORD).unwrap();
+
+        (public_account, account)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
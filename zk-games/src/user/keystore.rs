@@ -0,0 +1,219 @@
+use std::{fs, path::PathBuf};
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    json_file::write_json_atomic,
+    registry::get_public_account,
+    user::{AccountKdf, SECRET_FILENAME},
+    Config, Error, Result,
+};
+
+pub const KEYSTORE_FILENAME: &str = "keystore.json";
+pub const KEYSTORE_VERSION: u8 = 1;
+
+/// Local secret encrypted with a key derived from the player password
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Keystore {
+    pub version: u8,
+    /// Derives the encryption key, its salt is not the account salt
+    pub kdf: AccountKdf,
+    pub nonce: [u8; 12],
+    /// ChaCha20-Poly1305 of the secret, authenticated with the username
+    pub ciphertext: Vec<u8>,
+}
+
+impl Keystore {
+    pub fn encrypt(username: &str, password: &str, secret: &[u8; 32]) -> Result<Self> {
+        let kdf = AccountKdf::new_argon2id();
+        let nonce: [u8; 12] = rand::random();
+
+        let ciphertext = cipher(&kdf, username, password)?
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: secret,
+                    aad: username.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::Kdf("failed to encrypt the secret".to_string()))?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            kdf,
+            nonce,
+            ciphertext,
+        })
+    }
+
+    /// A wrong password fails the authentication of the ciphertext
+    pub fn decrypt(&self, username: &str, password: &str) -> Result<[u8; 32]> {
+        let secret = cipher(&self.kdf, username, password)?
+            .decrypt(
+                Nonce::from_slice(&self.nonce),
+                Payload {
+                    msg: &self.ciphertext,
+                    aad: username.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::InvalidPassword(username.to_string()))?;
+
+        secret
+            .as_slice()
+            .try_into()
+            .map_err(|_| Error::CorruptSecret(username.to_string()))
+    }
+}
+
+fn cipher(kdf: &AccountKdf, username: &str, password: &str) -> Result<ChaCha20Poly1305> {
+    let key = kdf.derive_secret(username, password)?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+fn keystore_path(config: &Config, username: &str) -> PathBuf {
+    config
+        .local_players_dir
+        .join(username)
+        .join(KEYSTORE_FILENAME)
+}
+
+fn legacy_secret_path(config: &Config, username: &str) -> PathBuf {
+    config
+        .local_players_dir
+        .join(username)
+        .join(SECRET_FILENAME)
+}
+
+/// Whether we have a local secret for this player, locked or not
+pub fn exists(config: &Config, username: &str) -> bool {
+    keystore_path(config, username).exists() || legacy_secret_path(config, username).exists()
+}
+
+fn read_keystore(config: &Config, username: &str) -> Result<Keystore> {
+    let file = fs::File::open(keystore_path(config, username))?;
+    serde_json::from_reader(file).map_err(|_| Error::CorruptSecret(username.to_string()))
+}
+
+/// Encrypt and save the secret, removes any plaintext secret left
+pub fn save(config: &Config, username: &str, password: &str, secret: &[u8; 32]) -> Result<()> {
+    let path = keystore_path(config, username);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let keystore = Keystore::encrypt(username, password, secret)?;
+    write_json_atomic(&path, &keystore)?;
+
+    // The plaintext secret is only dropped once the saved keystore gives it back
+    if read_keystore(config, username)?.decrypt(username, password)? != *secret {
+        return Err(Error::CorruptSecret(username.to_string()));
+    }
+    let legacy_path = legacy_secret_path(config, username);
+    if legacy_path.exists() {
+        fs::remove_file(legacy_path)?;
+    }
+
+    Ok(())
+}
+
+/// Decrypt the local secret, a plaintext legacy secret is encrypted on first unlock
+pub fn unlock(config: &Config, username: &str, password: &str) -> Result<[u8; 32]> {
+    let legacy_path = legacy_secret_path(config, username);
+    if keystore_path(config, username).exists() {
+        match read_keystore(config, username) {
+            // The plaintext secret is still there, the keystore is written again from it
+            Err(Error::CorruptSecret(_)) if legacy_path.exists() => (),
+            keystore => return keystore?.decrypt(username, password),
+        }
+    } else if !legacy_path.exists() {
+        return Err(Error::AccountNotFound(username.to_string()));
+    }
+
+    // Old clients may have written more than the secret, only its first 32 bytes count
+    let secret = *fs::read(legacy_path)?
+        .first_chunk::<32>()
+        .ok_or_else(|| Error::CorruptSecret(username.to_string()))?;
+
+    // Plaintext secrets can't check the password, so we check it against the
+    // public record before locking the secret with it
+    let account = get_public_account(config, username)?;
+    if account.kdf.derive_secret(username, password)? != secret {
+        return Err(Error::InvalidPassword(username.to_string()));
+    }
+
+    save(config, username, password, &secret)?;
+    Ok(secret)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{keystore_path, legacy_secret_path, unlock, Keystore};
+    use crate::{
+        registry::{save_public_account, PublicAccount},
+        testing::TestDir,
+        user::{generate_login_hash, AccountKdf},
+        Error,
+    };
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let secret = [42; 32];
+        let keystore = Keystore::encrypt("player", "password", &secret).unwrap();
+
+        assert_eq!(keystore.decrypt("player", "password").unwrap(), secret);
+        assert!(matches!(
+            keystore.decrypt("player", "wrong"),
+            Err(Error::InvalidPassword(_))
+        ));
+        // The ciphertext is bound to the username
+        assert!(keystore.decrypt("other", "password").is_err());
+    }
+
+    #[test]
+    fn test_unlock_legacy_secret() {
+        let dir = TestDir::new("legacy-secret");
+        let config = dir.config();
+
+        let secret = AccountKdf::Legacy
+            .derive_secret("player", "password")
+            .unwrap();
+        save_public_account(
+            &config,
+            &PublicAccount {
+                username: "player".to_string(),
+                kdf: AccountKdf::Legacy,
+                login_hash: generate_login_hash(secret),
+            },
+        )
+        .unwrap();
+
+        // Plaintext secret as old clients wrote it, with trailing bytes,
+        // next to a keystore whose write was interrupted
+        let path = legacy_secret_path(&config, "player");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, [secret.as_slice(), b"\n"].concat()).unwrap();
+        std::fs::write(keystore_path(&config, "player"), b"{\"version\":1,").unwrap();
+
+        assert!(matches!(
+            unlock(&config, "player", "wrong"),
+            Err(Error::InvalidPassword(_))
+        ));
+        assert_eq!(unlock(&config, "player", "password").unwrap(), secret);
+
+        // The secret moved to the keystore and still unlocks
+        assert!(!path.exists());
+        assert_eq!(unlock(&config, "player", "password").unwrap(), secret);
+
+        // Too short to hold a secret
+        std::fs::create_dir_all(config.local_players_dir.join("short")).unwrap();
+        std::fs::write(legacy_secret_path(&config, "short"), [0; 16]).unwrap();
+        assert!(matches!(
+            unlock(&config, "short", "password"),
+            Err(Error::CorruptSecret(_))
+        ));
+    }
+}
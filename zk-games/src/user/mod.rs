@@ -1,63 +1,296 @@
-use std::{
-    fs::File,
-    io::{BufWriter, Write},
-    path::Path,
-};
+pub mod keystore;
 
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
-pub const LOCAL_PLAYERS_PATH: &str = "/mnt/extra/Projects/solana/zk-games/data/local-players";
-pub const PUBLIC_PLAYERS_PATH: &str = "/mnt/extra/Projects/solana/zk-games/data/public-players";
+use crate::{
+    games::rps_basic::Game,
+    prover::{ProofMode, ProverBackend},
+    registry::{get_public_account, rotate_public_account, PublicAccount},
+    zk::{generate_rotate_proof, get_secret},
+    Config, Error, ProofVerifier, Result,
+};
+
+/// Plaintext secret of accounts created before the keystore
 pub const SECRET_FILENAME: &str = "secret";
-pub const LOGIN_HASH_FILENAME: &str = "login-hash";
 
-/// Very simple example to generate HASH from username and password
-fn generate_secret(username: &str, password: &str) -> [u8; 32] {
-    Sha256::new()
-        .chain_update(username)
-        .chain_update(password)
-        .finalize()
-        .into()
+/// A logged in player, holds the unlocked secret for the session
+#[derive(Clone)]
+pub struct Account {
+    pub username: String,
+    secret: [u8; 32],
+}
+
+impl Account {
+    /// Unlock the local keystore of the player
+    pub fn unlock(config: &Config, username: &str, password: &str) -> Result<Self> {
+        Ok(Account {
+            username: username.to_string(),
+            secret: get_secret(config, username, password)?,
+        })
+    }
+
+    pub fn secret(&self) -> &[u8; 32] {
+        &self.secret
+    }
+}
+
+/// How an account secret is derived from its password
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "version")]
+pub enum AccountKdf {
+    /// `sha256(username || password)`, only kept to migrate old accounts
+    Legacy,
+    /// Argon2id with a random salt
+    Argon2idV1 {
+        salt: [u8; 16],
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    },
+}
+
+impl AccountKdf {
+    /// Argon2id with a fresh salt and the default parameters
+    pub fn new_argon2id() -> Self {
+        AccountKdf::Argon2idV1 {
+            salt: rand::random(),
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+        }
+    }
+
+    /// Commits to the derivation, a rotation proof binds the new kdf with it
+    pub fn hash(&self) -> [u8; 32] {
+        match self {
+            AccountKdf::Legacy => Sha256::digest("legacy").into(),
+            AccountKdf::Argon2idV1 {
+                salt,
+                m_cost,
+                t_cost,
+                p_cost,
+            } => Sha256::new()
+                .chain_update("argon2id-v1")
+                .chain_update(salt)
+                .chain_update(m_cost.to_le_bytes())
+                .chain_update(t_cost.to_le_bytes())
+                .chain_update(p_cost.to_le_bytes())
+                .finalize()
+                .into(),
+        }
+    }
+
+    pub fn derive_secret(&self, username: &str, password: &str) -> Result<[u8; 32]> {
+        match self {
+            AccountKdf::Legacy => Ok(Sha256::new()
+                .chain_update(username)
+                .chain_update(password)
+                .finalize()
+                .into()),
+            AccountKdf::Argon2idV1 {
+                salt,
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                let params = Params::new(*m_cost, *t_cost, *p_cost, Some(32))
+                    .map_err(|e| Error::Kdf(e.to_string()))?;
+
+                let mut secret = [0; 32];
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(password.as_bytes(), salt, &mut secret)
+                    .map_err(|e| Error::Kdf(e.to_string()))?;
+                Ok(secret)
+            }
+        }
+    }
 }
 
 /// Takes secret and password and generates a public login hash
-fn generate_login_hash(secret: [u8; 32]) -> [u8; 32] {
+pub fn generate_login_hash(secret: [u8; 32]) -> [u8; 32] {
     Sha256::digest(secret).into()
 }
 
-/// Takes username and password and generates a secret and login hash
-fn create_account_hashes(username: &str, password: &str) -> ([u8; 32], [u8; 32]) {
-    let secret = generate_secret(username, password);
-    println!("secret: {secret:?}");
-    let login_hash = generate_login_hash(secret);
-    println!("login hash: {login_hash:?}");
-    (secret, login_hash)
+/// Create the account secret locally, returns the public record to register
+pub fn create_account(config: &Config, username: &str, password: &str) -> Result<PublicAccount> {
+    if keystore::exists(config, username) {
+        return Err(Error::AccountExists(username.to_string()));
+    }
+
+    let kdf = AccountKdf::new_argon2id();
+    let secret = kdf.derive_secret(username, password)?;
+
+    keystore::save(config, username, password, &secret)?;
+    Ok(PublicAccount {
+        username: username.to_string(),
+        kdf,
+        login_hash: generate_login_hash(secret),
+    })
 }
 
-fn save_secret(username: &str, secret: [u8; 32]) -> Result<(), String> {
-    let user_local_path = Path::new(LOCAL_PLAYERS_PATH).join(username);
+/// Rebuild the local secret of a registered account from its password.
+/// The secret must match the registered login hash before anything is written.
+pub fn recover_account(config: &Config, username: &str, password: &str) -> Result<Account> {
+    let account = get_public_account(config, username)?;
 
-    if !user_local_path.exists() {
-        std::fs::create_dir_all(&user_local_path).unwrap();
+    let secret = account.kdf.derive_secret(username, password)?;
+    if generate_login_hash(secret) != account.login_hash {
+        return Err(Error::InvalidPassword(username.to_string()));
     }
 
-    let filename = user_local_path.join(SECRET_FILENAME);
+    keystore::save(config, username, password, &secret)?;
+    Ok(Account {
+        username: username.to_string(),
+        secret,
+    })
+}
 
-    if filename.exists() {
-        return Err("Error: User already exists".to_string());
+/// Change the password of a logged in player, which rotates its secret.
+/// The registry only takes the new login hash with a proof of the old secret,
+/// checked by its verifier, the local keystore is replaced once the registry accepted it.
+/// Refused while `games` have commitments of the old secret left to reveal.
+pub fn rotate_account(
+    config: &Config,
+    prover: &ProverBackend,
+    verifier: &ProofVerifier,
+    account: &Account,
+    new_password: &str,
+    games: &[Game],
+    now: u64,
+) -> Result<Account> {
+    let username = account.username.as_str();
+    check_no_pending_commitments(username, games, now)?;
+    let current_account = get_public_account(config, username)?;
+
+    let kdf = AccountKdf::new_argon2id();
+    let secret = kdf.derive_secret(username, new_password)?;
+    let new_account = PublicAccount {
+        username: username.to_string(),
+        kdf,
+        login_hash: generate_login_hash(secret),
+    };
+
+    // The registry is meant to move on-chain, keep the rotation proof verifiable there
+    let proof = generate_rotate_proof(
+        prover,
+        account,
+        current_account.login_hash,
+        &new_account,
+        ProofMode::Groth16,
+    )?;
+    rotate_public_account(config, verifier, &new_account, &proof)?;
+
+    keystore::save(config, username, new_password, &secret)?;
+    Ok(Account {
+        username: username.to_string(),
+        secret,
+    })
+}
+
+/// Refuse to change the secret of `username` while one of `games` has a commitment
+/// made with it left to reveal, it could never be revealed afterwards
+fn check_no_pending_commitments(username: &str, games: &[Game], now: u64) -> Result<()> {
+    let pending = games
+        .iter()
+        .filter(|game| game.awaits_reveal_from(username, now))
+        .map(|game| game.id)
+        .collect::<Vec<_>>();
+    if !pending.is_empty() {
+        return Err(Error::PendingCommitments(username.to_string(), pending));
     }
 
-    // Save games to a file or database
-    let file = File::create(filename).unwrap();
-    let mut writer = BufWriter::new(file);
-    writer.write(&secret).unwrap();
-    writer.flush().unwrap();
     Ok(())
 }
 
-pub fn create_account(username: &str, password: &str) -> Result<[u8; 32], String> {
-    let (secret, login_hash) = create_account_hashes(username, password);
+/// Move a legacy account to the current kdf, keeping its password.
+/// The password must match the registered login hash, the new secret is registered
+/// like any rotation, and only once `games` have no commitment of the legacy secret left.
+pub fn migrate_account(
+    config: &Config,
+    prover: &ProverBackend,
+    verifier: &ProofVerifier,
+    username: &str,
+    password: &str,
+    games: &[Game],
+    now: u64,
+) -> Result<Account> {
+    let account = get_public_account(config, username)?;
+    if account.kdf != AccountKdf::Legacy {
+        return Account::unlock(config, username, password);
+    }
+
+    let legacy_secret = account.kdf.derive_secret(username, password)?;
+    if generate_login_hash(legacy_secret) != account.login_hash {
+        return Err(Error::InvalidPassword(username.to_string()));
+    }
+
+    let legacy_account = Account {
+        username: username.to_string(),
+        secret: legacy_secret,
+    };
+    rotate_account(
+        config,
+        prover,
+        verifier,
+        &legacy_account,
+        password,
+        games,
+        now,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_login_hash, recover_account, AccountKdf};
+    use crate::{
+        testing::{TestDir, TEST_PASSWORD},
+        Error,
+    };
 
-    save_secret(username, secret)?;
-    Ok(login_hash)
+    #[test]
+    fn test_argon2id_is_salted() {
+        let kdf = AccountKdf::new_argon2id();
+        let secret = kdf.derive_secret("player", "password").unwrap();
+
+        // Same salt, same secret
+        assert_eq!(secret, kdf.derive_secret("player", "password").unwrap());
+        // Other salt, other secret
+        let other = AccountKdf::new_argon2id();
+        assert_ne!(secret, other.derive_secret("player", "password").unwrap());
+
+        // Rotation proofs tell them apart by their hash
+        assert_eq!(kdf.hash(), kdf.clone().hash());
+        assert_ne!(kdf.hash(), other.hash());
+        assert_ne!(kdf.hash(), AccountKdf::Legacy.hash());
+    }
+
+    #[test]
+    fn test_recover_account() {
+        let dir = TestDir::new("recover");
+        let config = dir.config();
+        let (account, _) = dir.player("player");
+
+        // Lose the local secret
+        std::fs::remove_dir_all(&config.local_players_dir).unwrap();
+
+        assert!(matches!(
+            recover_account(&config, "player", "wrong"),
+            Err(Error::InvalidPassword(_))
+        ));
+        assert!(!config.local_players_dir.join("player").exists());
+
+        let recovered = recover_account(&config, "player", TEST_PASSWORD).unwrap();
+        assert_eq!(generate_login_hash(*recovered.secret()), account.login_hash);
+    }
+
+    #[test]
+    fn test_legacy_ambiguity() {
+        // The legacy kdf can't tell where the username ends
+        assert_eq!(
+            AccountKdf::Legacy.derive_secret("ab", "c").unwrap(),
+            AccountKdf::Legacy.derive_secret("a", "bc").unwrap()
+        );
+    }
 }
@@ -0,0 +1,93 @@
+use sp1_sdk::{Prover, ProverClient, SP1ProofWithPublicValues};
+use sp1_verifier::{Groth16Verifier, PlonkVerifier, GROTH16_VK_BYTES, PLONK_VK_BYTES};
+
+use crate::{programs::Program, prover::ProofMode, Error, Result};
+
+/// Checks proofs against the vk of their program, whatever backend generated them.
+/// Which proofs are accepted never depends on the prover setting.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofVerifier {
+    /// Every proof is checked, the only verifier to use outside of tests
+    #[default]
+    Strict,
+    /// Also accepts mock proofs, which prove nothing, for tests running the mock prover
+    AcceptMock,
+}
+
+impl ProofVerifier {
+    /// Check an encoded proof (see [`ProofMode::encode`]) of the program against `vk_hash`.
+    /// SNARKs can be checked against any build of the program, STARKs only against this one.
+    pub fn verify_encoded(
+        &self,
+        program: &Program,
+        vk_hash: &str,
+        mode: ProofMode,
+        proof: &[u8],
+        public_values: &[u8],
+    ) -> Result<()> {
+        match mode {
+            ProofMode::Core | ProofMode::Compressed => {
+                // The whole vk is needed, we only have the one of our build
+                if vk_hash != program.vk_hash() {
+                    return Err(Error::Verification(format!(
+                        "{} STARK proofs can only be checked against vk {}",
+                        program.name,
+                        program.vk_hash()
+                    )));
+                }
+
+                let proof: SP1ProofWithPublicValues = bincode::deserialize(proof)
+                    .map_err(|e| Error::Verification(format!("malformed proof: {e}")))?;
+                // The public values are part of the STARK, they must be the ones we read
+                if proof.public_values.as_slice() != public_values {
+                    return Err(Error::Verification(
+                        "proof doesn't commit to these public values".to_string(),
+                    ));
+                }
+
+                self.verify(&proof, program)
+            }
+            // Mock SNARKs encode to nothing
+            ProofMode::Plonk | ProofMode::Groth16
+                if proof.is_empty() && *self == ProofVerifier::AcceptMock =>
+            {
+                Ok(())
+            }
+            ProofMode::Plonk => {
+                PlonkVerifier::verify(proof, public_values, vk_hash, *PLONK_VK_BYTES)
+                    .map_err(|e| Error::Verification(e.to_string()))
+            }
+            ProofMode::Groth16 => {
+                Groth16Verifier::verify(proof, public_values, vk_hash, *GROTH16_VK_BYTES)
+                    .map_err(|e| Error::Verification(e.to_string()))
+            }
+        }
+    }
+
+    /// Check a proof of this build of the program
+    pub fn verify(&self, proof: &SP1ProofWithPublicValues, program: &Program) -> Result<()> {
+        let mode = ProofMode::of(proof);
+        match mode {
+            ProofMode::Core | ProofMode::Compressed => {
+                let res = match self {
+                    ProofVerifier::Strict => ProverClient::builder()
+                        .cpu()
+                        .build()
+                        .verify(proof, program.vk()),
+                    ProofVerifier::AcceptMock => ProverClient::builder()
+                        .mock()
+                        .build()
+                        .verify(proof, program.vk()),
+                };
+                res.map_err(|e| Error::Verification(e.to_string()))
+            }
+            ProofMode::Plonk | ProofMode::Groth16 => self.verify_encoded(
+                program,
+                program.vk_hash(),
+                mode,
+                &proof.bytes(),
+                proof.public_values.as_slice(),
+            ),
+        }
+    }
+}
@@ -1,17 +1,61 @@
-use std::path::Path;
-
-use crate::user::{LOCAL_PLAYERS_PATH, SECRET_FILENAME};
-
-pub fn get_secret(username: &str) -> Result<[u8; 32], String> {
-    let secret_path = Path::new(LOCAL_PLAYERS_PATH)
-        .join(username)
-        .join(SECRET_FILENAME);
-    if !secret_path.exists() {
-        return Err("Secret doesn't exists, please try to recover".to_string());
-    }
-    Ok(*std::fs::read(secret_path)
-        .unwrap()
-        .as_slice()
-        .first_chunk::<32>()
-        .expect("secret should be length of 32"))
+use sp1_sdk::{SP1ProofWithPublicValues, SP1Stdin};
+use zk_games_types::{LoginInput, RotateInput};
+
+use crate::{
+    login::LoginChallenge,
+    programs,
+    prover::{ProofMode, ProverBackend},
+    registry::PublicAccount,
+    user::{keystore, Account},
+    Config, Result,
+};
+
+/// Unlock the local secret of the player with its password
+pub fn get_secret(config: &Config, username: &str, password: &str) -> Result<[u8; 32]> {
+    keystore::unlock(config, username, password)
+}
+
+/// Prove we know the secret behind `login_hash` to answer this login challenge
+pub fn generate_login_proof(
+    prover: &ProverBackend,
+    account: &Account,
+    login_hash: [u8; 32],
+    challenge: &LoginChallenge,
+    mode: ProofMode,
+) -> Result<SP1ProofWithPublicValues> {
+    let login_input = LoginInput {
+        username: account.username.clone(),
+        login_hash,
+        random_string: challenge.random_string.clone(),
+        client_pubkey: challenge.client_pubkey.clone(),
+        expires_at: challenge.expires_at,
+        secret: *account.secret(),
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&login_input);
+
+    prover.prove(&programs::LOGIN, &stdin, mode)
+}
+
+/// Prove we know the secret behind `old_login_hash` to register `new_account` instead
+pub fn generate_rotate_proof(
+    prover: &ProverBackend,
+    account: &Account,
+    old_login_hash: [u8; 32],
+    new_account: &PublicAccount,
+    mode: ProofMode,
+) -> Result<SP1ProofWithPublicValues> {
+    let rotate_input = RotateInput {
+        username: account.username.clone(),
+        old_login_hash,
+        new_login_hash: new_account.login_hash,
+        new_kdf_hash: new_account.kdf.hash(),
+        old_secret: *account.secret(),
+    };
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&rotate_input);
+
+    prover.prove(&programs::ROTATE, &stdin, mode)
 }